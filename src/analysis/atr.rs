@@ -42,3 +42,27 @@ pub fn compute_atr(bars: &[Bar], period: usize) -> Vec<f64> {
 
     atr_values
 }
+
+/// Wilder ATR that uses only bars up to each index. The first `period - 1`
+/// values are the running mean of the true ranges seen so far instead of
+/// being back-filled from later bars.
+pub fn compute_causal_atr(bars: &[Bar], period: usize) -> Vec<f64> {
+    if period == 0 {
+        return Vec::new();
+    }
+    let mut atr_values = Vec::with_capacity(bars.len());
+    let mut prev_atr = 0.0;
+    for (idx, bar) in bars.iter().enumerate() {
+        let tr = match idx.checked_sub(1).map(|prev| bars[prev].close) {
+            Some(prev_close) => (bar.high - bar.low)
+                .max((bar.high - prev_close).abs())
+                .max((bar.low - prev_close).abs()),
+            None => bar.high - bar.low,
+        }
+        .max(0.0);
+        let weight = (idx + 1).min(period) as f64;
+        prev_atr = (prev_atr * (weight - 1.0) + tr) / weight;
+        atr_values.push(prev_atr);
+    }
+    atr_values
+}
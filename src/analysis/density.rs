@@ -1,11 +1,230 @@
 use std::f64::consts::PI;
 
+use clap::ValueEnum;
+use statrs::function::erf::erf;
+
+use crate::analysis::fft::convolve_symmetric;
 use crate::data::{DensityPoint, SwingPoint};
 
+/// Kernel evaluation work (grid points x swings x bandwidths) above which the
+/// `auto` engine leaves the exact path.
+const FAST_KDE_WORK_THRESHOLD: usize = 2_000_000;
+
+/// Gaussian kernels are cut at this many local bandwidths by the fast paths;
+/// the discarded tail mass is below 1e-6.
+const KERNEL_TRUNCATION_SIGMAS: f64 = 5.0;
+
+/// Upper bound on the grid used for the Abramson pilot density.
+const PILOT_GRID_LIMIT: usize = 1 << 16;
+
+/// Smoothing kernel. Every kernel is scaled to unit variance so a bandwidth
+/// from any selector means the same amount of smoothing whichever kernel is
+/// used; the compact kernels reach `support()` bandwidths from the swing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Kernel {
+    Gaussian,
+    Epanechnikov,
+    Triweight,
+    Tophat,
+}
+
+impl Kernel {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Kernel::Gaussian => "gaussian",
+            Kernel::Epanechnikov => "epanechnikov",
+            Kernel::Triweight => "triweight",
+            Kernel::Tophat => "tophat",
+        }
+    }
+
+    /// Half-width of the kernel in bandwidth units (truncation point for Gaussian).
+    pub fn support(&self) -> f64 {
+        match self {
+            Kernel::Gaussian => KERNEL_TRUNCATION_SIGMAS,
+            Kernel::Epanechnikov => 5.0_f64.sqrt(),
+            Kernel::Triweight => 3.0,
+            Kernel::Tophat => 3.0_f64.sqrt(),
+        }
+    }
+
+    /// Kernel density at `z` bandwidths from the centre.
+    pub fn eval(&self, z: f64) -> f64 {
+        match self {
+            Kernel::Gaussian => (-0.5 * z * z).exp() / (2.0 * PI).sqrt(),
+            Kernel::Epanechnikov => {
+                let radius = self.support();
+                let u = z / radius;
+                if u.abs() < 1.0 {
+                    0.75 * (1.0 - u * u) / radius
+                } else {
+                    0.0
+                }
+            }
+            Kernel::Triweight => {
+                let radius = self.support();
+                let u = z / radius;
+                if u.abs() < 1.0 {
+                    35.0 / 32.0 * (1.0 - u * u).powi(3) / radius
+                } else {
+                    0.0
+                }
+            }
+            Kernel::Tophat => {
+                let radius = self.support();
+                if z.abs() < radius {
+                    0.5 / radius
+                } else {
+                    0.0
+                }
+            }
+        }
+    }
+
+    /// Kernel mass below `z` bandwidths from the centre.
+    pub fn cdf(&self, z: f64) -> f64 {
+        if *self == Kernel::Gaussian {
+            return 0.5 * (1.0 + erf(z / 2.0_f64.sqrt()));
+        }
+        let u = (z / self.support()).clamp(-1.0, 1.0);
+        match self {
+            Kernel::Epanechnikov => 0.5 + 0.75 * (u - u.powi(3) / 3.0),
+            Kernel::Triweight => {
+                0.5 + 35.0 / 32.0 * (u - u.powi(3) + 0.6 * u.powi(5) - u.powi(7) / 7.0)
+            }
+            _ => 0.5 * (u + 1.0),
+        }
+    }
+}
+
+/// Correction for the kernel mass that spills past the lowest and highest swing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum BoundaryCorrection {
+    /// Let kernels spill past the swing range (density tapers at the edges).
+    None,
+    /// Mirror swings about the lowest and highest swing price.
+    Reflection,
+    /// Scale each swing's kernel by the inverse of its mass inside the swing range.
+    Renormalization,
+}
+
+impl BoundaryCorrection {
+    pub fn label(&self) -> &'static str {
+        match self {
+            BoundaryCorrection::None => "none",
+            BoundaryCorrection::Reflection => "reflection",
+            BoundaryCorrection::Renormalization => "renormalization",
+        }
+    }
+}
+
+/// Bandwidth selection rule used by the swing-price KDE.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum BandwidthMethod {
+    /// Weighted Scott rule averaged over 0.75x, 1x and 1.5x multiples.
+    Scott,
+    /// Silverman's rule of thumb using the smaller of sigma and IQR/1.34.
+    Silverman,
+    /// Improved Sheather-Jones plug-in (Botev et al. fixed-point solver).
+    SheatherJones,
+    /// Likelihood (leave-one-out) cross-validation.
+    LikelihoodCv,
+    /// Fixed bandwidth in price units.
+    Fixed,
+    /// Fixed bandwidth in multiples of the mean ATR.
+    Atr,
+}
+
+impl BandwidthMethod {
+    pub fn label(&self) -> &'static str {
+        match self {
+            BandwidthMethod::Scott => "scott",
+            BandwidthMethod::Silverman => "silverman",
+            BandwidthMethod::SheatherJones => "sheather-jones",
+            BandwidthMethod::LikelihoodCv => "likelihood-cv",
+            BandwidthMethod::Fixed => "fixed",
+            BandwidthMethod::Atr => "atr",
+        }
+    }
+}
+
+/// Per-swing bandwidth adaptation applied on top of the global bandwidth.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum AdaptiveMode {
+    /// Single global bandwidth for every swing.
+    None,
+    /// Abramson square-root law from a fixed-bandwidth pilot estimate.
+    Abramson,
+    /// Bandwidth proportional to the distance to the k-th nearest swing (k = sqrt(n)).
+    Knn,
+}
+
+impl AdaptiveMode {
+    pub fn label(&self) -> &'static str {
+        match self {
+            AdaptiveMode::None => "none",
+            AdaptiveMode::Abramson => "abramson",
+            AdaptiveMode::Knn => "knn",
+        }
+    }
+}
+
+/// Evaluation strategy for the density grid.
+///
+/// The binned path linearly bins swing weights onto the grid and convolves with
+/// the sampled kernel via FFT; with a grid step of at most an eighth of the
+/// bandwidth it matches the exact sum to within 1e-3 of the peak density. It
+/// needs a single bandwidth per swing, so adaptive runs use the truncated
+/// path, which only visits grid points within five local bandwidths of each
+/// swing and stays within 1e-6 of the exact sum. A requested binned engine
+/// also falls back to truncated when the grid is coarser than that.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum KdeEngine {
+    /// Pick exact for small problems, otherwise binned or truncated.
+    Auto,
+    /// Direct kernel sum at every grid point.
+    Exact,
+    /// Linear binning plus FFT convolution.
+    Binned,
+    /// Direct sum restricted to the kernel's effective support.
+    Truncated,
+}
+
+impl KdeEngine {
+    pub fn label(&self) -> &'static str {
+        match self {
+            KdeEngine::Auto => "auto",
+            KdeEngine::Exact => "exact",
+            KdeEngine::Binned => "binned-fft",
+            KdeEngine::Truncated => "truncated",
+        }
+    }
+}
+
+/// Options controlling how the swing-price density is estimated.
+#[derive(Debug, Clone, Copy)]
+pub struct DensitySettings {
+    pub grid_points: usize,
+    pub bandwidth_method: BandwidthMethod,
+    /// Bandwidth in price units (`Fixed`) or ATR multiples (`Atr`).
+    pub bandwidth_value: f64,
+    pub mean_atr: f64,
+    pub adaptive: AdaptiveMode,
+    /// Scale each swing's kernel by its ATR relative to the swing-average ATR.
+    pub atr_scaled_kernels: bool,
+    pub engine: KdeEngine,
+    pub kernel: Kernel,
+    pub boundary: BoundaryCorrection,
+}
+
 #[derive(Debug, Clone)]
 pub struct DensityAnalysis {
     pub grid: Vec<DensityPoint>,
     pub bandwidths: Vec<f64>,
+    pub bandwidth_label: String,
+    pub engine: KdeEngine,
+    pub kernel: Kernel,
+    pub boundary: BoundaryCorrection,
     pub max_density: f64,
 }
 
@@ -13,21 +232,31 @@ impl DensityAnalysis {
     pub fn is_empty(&self) -> bool {
         self.grid.is_empty()
     }
-}
 
-pub fn compute_density_curve(swings: &[SwingPoint], grid_points: usize) -> DensityAnalysis {
-    if swings.is_empty() || grid_points < 3 {
-        return DensityAnalysis {
+    fn empty() -> Self {
+        Self {
             grid: Vec::new(),
             bandwidths: Vec::new(),
+            bandwidth_label: String::new(),
+            engine: KdeEngine::Exact,
+            kernel: Kernel::Gaussian,
+            boundary: BoundaryCorrection::None,
             max_density: 0.0,
-        };
+        }
+    }
+}
+
+pub fn compute_density_curve(swings: &[SwingPoint], settings: &DensitySettings) -> DensityAnalysis {
+    let grid_points = settings.grid_points;
+    if swings.is_empty() || grid_points < 3 {
+        return DensityAnalysis::empty();
     }
 
     let mut min_price = f64::MAX;
     let mut max_price = f64::MIN;
     let mut weights = Vec::with_capacity(swings.len());
     let mut prices = Vec::with_capacity(swings.len());
+    let mut swing_atrs = Vec::with_capacity(swings.len());
     for swing in swings {
         let price = swing.price;
         if !price.is_finite() {
@@ -38,14 +267,11 @@ pub fn compute_density_curve(swings: &[SwingPoint], grid_points: usize) -> Densi
         max_price = max_price.max(price);
         weights.push(weight);
         prices.push(price);
+        swing_atrs.push(swing.atr);
     }
 
     if prices.len() < 2 {
-        return DensityAnalysis {
-            grid: Vec::new(),
-            bandwidths: Vec::new(),
-            max_density: 0.0,
-        };
+        return DensityAnalysis::empty();
     }
 
     let total_weight: f64 = weights.iter().sum();
@@ -62,14 +288,27 @@ pub fn compute_density_curve(swings: &[SwingPoint], grid_points: usize) -> Densi
         .sum::<f64>()
         / total_weight;
     let std_dev = variance.sqrt().max(1e-6);
-    let n = prices.len() as f64;
-    let base_bandwidth = 1.06 * std_dev * n.powf(-0.2);
-
-    let mut bandwidths = vec![base_bandwidth * 0.75, base_bandwidth, base_bandwidth * 1.5];
+    let (bandwidth_label, mut bandwidths) =
+        select_bandwidths(settings, &prices, &weights, total_weight, std_dev);
     bandwidths.retain(|bw| bw.is_finite() && *bw > 0.0);
     bandwidths.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
     bandwidths.dedup_by(|a, b| (*a - *b).abs() < 1e-6);
 
+    let pilot_bandwidth = bandwidths
+        .get(bandwidths.len() / 2)
+        .copied()
+        .unwrap_or(std_dev);
+    let factors = local_bandwidth_factors(
+        settings,
+        &prices,
+        &weights,
+        &swing_atrs,
+        pilot_bandwidth,
+        total_weight,
+    );
+    let bandwidth_label = describe_adaptation(settings, bandwidth_label, &factors);
+
+    let (lower_bound, upper_bound) = (min_price, max_price);
     let margin = (max_price - min_price).abs().max(std_dev) * 0.15;
     min_price -= margin;
     max_price += margin;
@@ -79,21 +318,102 @@ pub fn compute_density_curve(swings: &[SwingPoint], grid_points: usize) -> Densi
         0.0
     };
 
+    if bandwidths.is_empty() {
+        bandwidths.push(std_dev);
+    }
+    let kernel = settings.kernel;
+    let engine = resolve_engine(
+        settings.engine,
+        kernel,
+        grid_points,
+        prices.len(),
+        &bandwidths,
+        &factors,
+        step,
+    );
+
+    let mut densities = vec![0.0; grid_points];
+    for &bandwidth in &bandwidths {
+        let (points, point_weights, point_factors) =
+            if settings.boundary == BoundaryCorrection::Reflection {
+                reflect_at_boundaries(
+                    kernel,
+                    &prices,
+                    &weights,
+                    &factors,
+                    bandwidth,
+                    lower_bound,
+                    upper_bound,
+                )
+            } else if settings.boundary == BoundaryCorrection::Renormalization {
+                (
+                    prices.clone(),
+                    renormalized_weights(
+                        kernel,
+                        &prices,
+                        &weights,
+                        &factors,
+                        bandwidth,
+                        (lower_bound, upper_bound),
+                    ),
+                    factors.clone(),
+                )
+            } else {
+                (prices.clone(), weights.clone(), factors.clone())
+            };
+        let contribution: Vec<f64> = match engine {
+            KdeEngine::Binned => binned_density(
+                kernel,
+                &points,
+                &point_weights,
+                bandwidth,
+                total_weight,
+                min_price,
+                step,
+                grid_points,
+            ),
+            KdeEngine::Truncated => truncated_density(
+                kernel,
+                &points,
+                &point_weights,
+                &point_factors,
+                bandwidth,
+                total_weight,
+                min_price,
+                step,
+                grid_points,
+            ),
+            KdeEngine::Exact | KdeEngine::Auto => (0..grid_points)
+                .map(|idx| {
+                    let price = min_price + step * idx as f64;
+                    kernel_sum(
+                        kernel,
+                        price,
+                        &points,
+                        &point_weights,
+                        &point_factors,
+                        bandwidth,
+                        total_weight,
+                    )
+                })
+                .collect(),
+        };
+        for (density, value) in densities.iter_mut().zip(contribution) {
+            *density += value;
+        }
+    }
+
+    let valid_bandwidths = bandwidths.len() as f64;
     let mut grid = Vec::with_capacity(grid_points);
     let mut max_density: f64 = 0.0;
-
-    let valid_bandwidths = bandwidths.len().max(1) as f64;
-    for idx in 0..grid_points {
+    for (idx, density) in densities.into_iter().enumerate() {
         let price = min_price + step * idx as f64;
-        let mut density = 0.0;
-        for &bandwidth in &bandwidths {
-            density += gaussian_kernel_sum(price, &prices, &weights, bandwidth, total_weight);
-        }
-        if bandwidths.is_empty() {
-            density = gaussian_kernel_sum(price, &prices, &weights, std_dev, total_weight);
+        let outside = price < lower_bound - 1e-9 || price > upper_bound + 1e-9;
+        let density = if settings.boundary != BoundaryCorrection::None && outside {
+            0.0
         } else {
-            density /= valid_bandwidths;
-        }
+            (density / valid_bandwidths).max(0.0)
+        };
         max_density = max_density.max(density);
         grid.push(DensityPoint { price, density });
     }
@@ -101,25 +421,595 @@ pub fn compute_density_curve(swings: &[SwingPoint], grid_points: usize) -> Densi
     DensityAnalysis {
         grid,
         bandwidths,
+        bandwidth_label,
+        engine,
+        kernel,
+        boundary: settings.boundary,
         max_density,
     }
 }
 
-fn gaussian_kernel_sum(
+/// Reflection boundary correction: add mirror images of the swings within
+/// kernel reach of the lowest or highest swing. Grid points outside the swing
+/// range are zeroed by the caller for every correction mode.
+#[allow(clippy::too_many_arguments)]
+fn reflect_at_boundaries(
+    kernel: Kernel,
+    prices: &[f64],
+    weights: &[f64],
+    factors: &[f64],
+    bandwidth: f64,
+    lower_bound: f64,
+    upper_bound: f64,
+) -> (Vec<f64>, Vec<f64>, Vec<f64>) {
+    let mut points = prices.to_vec();
+    let mut point_weights = weights.to_vec();
+    let mut point_factors = factors.to_vec();
+    for ((price, weight), factor) in prices.iter().zip(weights.iter()).zip(factors.iter()) {
+        let reach = kernel.support() * bandwidth * factor;
+        for mirror in [2.0 * lower_bound - price, 2.0 * upper_bound - price] {
+            if (mirror - price).abs() <= 2.0 * reach {
+                points.push(mirror);
+                point_weights.push(*weight);
+                point_factors.push(*factor);
+            }
+        }
+    }
+    (points, point_weights, point_factors)
+}
+
+/// Cut-and-normalise correction: divide each swing's weight by the share of
+/// its own kernel, at its local bandwidth, that falls inside the swing range.
+/// Every swing lies inside the range, so that share is at least one half.
+fn renormalized_weights(
+    kernel: Kernel,
+    prices: &[f64],
+    weights: &[f64],
+    factors: &[f64],
+    bandwidth: f64,
+    bounds: (f64, f64),
+) -> Vec<f64> {
+    let (lower_bound, upper_bound) = bounds;
+    prices
+        .iter()
+        .zip(weights.iter())
+        .zip(factors.iter())
+        .map(|((price, weight), factor)| {
+            let local = bandwidth * factor;
+            let mass = kernel.cdf((upper_bound - price) / local)
+                - kernel.cdf((lower_bound - price) / local);
+            weight / mass
+        })
+        .collect()
+}
+
+fn resolve_engine(
+    requested: KdeEngine,
+    kernel: Kernel,
+    grid_points: usize,
+    swing_count: usize,
+    bandwidths: &[f64],
+    factors: &[f64],
+    step: f64,
+) -> KdeEngine {
+    let uniform = factors.iter().all(|factor| (factor - 1.0).abs() < 1e-12);
+    let smallest = bandwidths.iter().copied().fold(f64::MAX, f64::min);
+    match requested {
+        KdeEngine::Auto => {
+            let work = grid_points * swing_count * bandwidths.len();
+            if work <= FAST_KDE_WORK_THRESHOLD {
+                KdeEngine::Exact
+            } else if kernel == Kernel::Gaussian && uniform && step <= smallest * 0.125 {
+                KdeEngine::Binned
+            } else {
+                KdeEngine::Truncated
+            }
+        }
+        KdeEngine::Binned if !uniform || step > smallest * 0.125 => KdeEngine::Truncated,
+        other => other,
+    }
+}
+
+/// Linearly bin the weights onto the grid (extended by the kernel reach so
+/// swings just off the grid still contribute) and convolve with the sampled
+/// kernel.
+#[allow(clippy::too_many_arguments)]
+fn binned_density(
+    kernel: Kernel,
+    prices: &[f64],
+    weights: &[f64],
+    bandwidth: f64,
+    total_weight: f64,
+    origin: f64,
+    step: f64,
+    grid_points: usize,
+) -> Vec<f64> {
+    if bandwidth <= 0.0 || step <= 0.0 {
+        return vec![0.0; grid_points];
+    }
+    let reach = ((kernel.support() * bandwidth / step).ceil() as usize).min(grid_points);
+    let extended = grid_points + 2 * reach;
+    let extended_origin = origin - reach as f64 * step;
+    let mut bins = vec![0.0; extended];
+    for (price, weight) in prices.iter().zip(weights.iter()) {
+        let position = (price - extended_origin) / step;
+        if position < 0.0 || position > (extended - 1) as f64 {
+            continue;
+        }
+        let left = (position.floor() as usize).min(extended - 1);
+        let fraction = position - left as f64;
+        bins[left] += weight * (1.0 - fraction);
+        if left + 1 < extended {
+            bins[left + 1] += weight * fraction;
+        }
+    }
+
+    let norm = 1.0 / (total_weight * bandwidth);
+    let taps: Vec<f64> = (0..=reach)
+        .map(|lag| norm * kernel.eval(lag as f64 * step / bandwidth))
+        .collect();
+    let mut densities = convolve_symmetric(&bins, &taps);
+    densities.drain(..reach);
+    densities.truncate(grid_points);
+    densities
+}
+
+/// Scatter each swing's kernel onto the grid points within its effective support.
+#[allow(clippy::too_many_arguments)]
+fn truncated_density(
+    kernel: Kernel,
+    prices: &[f64],
+    weights: &[f64],
+    factors: &[f64],
+    bandwidth: f64,
+    total_weight: f64,
+    origin: f64,
+    step: f64,
+    grid_points: usize,
+) -> Vec<f64> {
+    let mut densities = vec![0.0; grid_points];
+    if bandwidth <= 0.0 || step <= 0.0 {
+        return densities;
+    }
+    let last = (grid_points - 1) as f64;
+    for ((price, weight), factor) in prices.iter().zip(weights.iter()).zip(factors.iter()) {
+        let local = bandwidth * factor;
+        let reach = kernel.support() * local;
+        let start = ((price - reach - origin) / step).ceil().clamp(0.0, last) as usize;
+        let end = ((price + reach - origin) / step).floor().clamp(0.0, last) as usize;
+        for (idx, density) in densities.iter_mut().enumerate().take(end + 1).skip(start) {
+            let z = (origin + step * idx as f64 - price) / local;
+            *density += weight * kernel.eval(z) / (local * total_weight);
+        }
+    }
+    densities
+}
+
+/// Resolve the configured bandwidth rule into the set of bandwidths to average
+/// over, together with a label describing what was actually used.
+fn select_bandwidths(
+    settings: &DensitySettings,
+    prices: &[f64],
+    weights: &[f64],
+    total_weight: f64,
+    std_dev: f64,
+) -> (String, Vec<f64>) {
+    let n_eff = effective_sample_size(weights, total_weight);
+    let silverman = silverman_bandwidth(prices, weights, std_dev, n_eff);
+    match settings.bandwidth_method {
+        BandwidthMethod::Scott => {
+            let n = prices.len() as f64;
+            let base = 1.06 * std_dev * n.powf(-0.2);
+            (
+                BandwidthMethod::Scott.label().to_string(),
+                vec![base * 0.75, base, base * 1.5],
+            )
+        }
+        BandwidthMethod::Silverman => (
+            BandwidthMethod::Silverman.label().to_string(),
+            vec![silverman],
+        ),
+        BandwidthMethod::SheatherJones => {
+            match sheather_jones_bandwidth(prices, weights, total_weight, n_eff) {
+                Some(bw) => (BandwidthMethod::SheatherJones.label().to_string(), vec![bw]),
+                None => (
+                    "sheather-jones (no root; silverman fallback)".to_string(),
+                    vec![silverman],
+                ),
+            }
+        }
+        BandwidthMethod::LikelihoodCv => (
+            BandwidthMethod::LikelihoodCv.label().to_string(),
+            vec![likelihood_cv_bandwidth(prices, weights, silverman)],
+        ),
+        BandwidthMethod::Fixed => {
+            if settings.bandwidth_value.is_finite() && settings.bandwidth_value > 0.0 {
+                (
+                    BandwidthMethod::Fixed.label().to_string(),
+                    vec![settings.bandwidth_value],
+                )
+            } else {
+                (
+                    "fixed (invalid value; silverman fallback)".to_string(),
+                    vec![silverman],
+                )
+            }
+        }
+        BandwidthMethod::Atr => {
+            let bw = settings.mean_atr * settings.bandwidth_value;
+            if bw.is_finite() && bw > 0.0 {
+                (format!("atr x{:.2}", settings.bandwidth_value), vec![bw])
+            } else {
+                (
+                    "atr (no ATR available; silverman fallback)".to_string(),
+                    vec![silverman],
+                )
+            }
+        }
+    }
+}
+
+/// Kish effective sample size of a weighted sample.
+fn effective_sample_size(weights: &[f64], total_weight: f64) -> f64 {
+    let sum_sq: f64 = weights.iter().map(|w| w * w).sum();
+    if sum_sq > 0.0 {
+        (total_weight * total_weight / sum_sq).max(2.0)
+    } else {
+        weights.len().max(2) as f64
+    }
+}
+
+fn silverman_bandwidth(prices: &[f64], weights: &[f64], std_dev: f64, n_eff: f64) -> f64 {
+    let iqr = weighted_quantile(prices, weights, 0.75) - weighted_quantile(prices, weights, 0.25);
+    let spread = if iqr > 0.0 {
+        std_dev.min(iqr / 1.34)
+    } else {
+        std_dev
+    };
+    0.9 * spread * n_eff.powf(-0.2)
+}
+
+fn weighted_quantile(prices: &[f64], weights: &[f64], q: f64) -> f64 {
+    let mut pairs: Vec<(f64, f64)> = prices
+        .iter()
+        .copied()
+        .zip(weights.iter().copied())
+        .collect();
+    pairs.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+    let total: f64 = pairs.iter().map(|(_, w)| w).sum();
+    let target = q.clamp(0.0, 1.0) * total;
+    let mut cumulative = 0.0;
+    for (price, weight) in &pairs {
+        cumulative += weight;
+        if cumulative >= target {
+            return *price;
+        }
+    }
+    pairs.last().map(|(price, _)| *price).unwrap_or_default()
+}
+
+/// Improved Sheather-Jones plug-in bandwidth (Botev, Grotowski & Kroese 2010).
+///
+/// The weighted sample is binned onto a regular mesh, transformed with a DCT and
+/// the fixed-point equation `t = xi * gamma^[l](t)` is solved by bisection.
+fn sheather_jones_bandwidth(
+    prices: &[f64],
+    weights: &[f64],
+    total_weight: f64,
+    n_eff: f64,
+) -> Option<f64> {
+    const MESH: usize = 512;
+    let min = prices.iter().copied().fold(f64::MAX, f64::min);
+    let max = prices.iter().copied().fold(f64::MIN, f64::max);
+    let range = max - min;
+    if range <= 0.0 || total_weight <= 0.0 {
+        return None;
+    }
+    let lower = min - range / 2.0;
+    let span = 2.0 * range;
+
+    let mut histogram = vec![0.0; MESH];
+    for (price, weight) in prices.iter().zip(weights.iter()) {
+        let position = ((price - lower) / span * MESH as f64).floor() as usize;
+        histogram[position.min(MESH - 1)] += weight / total_weight;
+    }
+
+    // DCT-II coefficients; a2[k] holds the squared coefficient for frequency k.
+    let mut a2 = vec![0.0; MESH];
+    for (k, slot) in a2.iter_mut().enumerate().skip(1) {
+        let mut coefficient = 0.0;
+        for (j, value) in histogram.iter().enumerate() {
+            if *value != 0.0 {
+                coefficient +=
+                    value * (PI * k as f64 * (2.0 * j as f64 + 1.0) / (2.0 * MESH as f64)).cos();
+            }
+        }
+        *slot = coefficient * coefficient;
+    }
+
+    let fixed_point = |t: f64| -> f64 {
+        let l = 7;
+        let mut f = 2.0
+            * PI.powi(2 * l)
+            * (1..MESH)
+                .map(|k| {
+                    let i = (k * k) as f64;
+                    i.powi(l) * a2[k] * (-i * PI * PI * t).exp()
+                })
+                .sum::<f64>();
+        for s in (2..l).rev() {
+            let k0 = (1..=s).map(|j| (2 * j - 1) as f64).product::<f64>() / (2.0 * PI).sqrt();
+            let constant = (1.0 + 0.5_f64.powf(s as f64 + 0.5)) / 3.0;
+            let time = (2.0 * constant * k0 / n_eff / f).powf(2.0 / (3.0 + 2.0 * s as f64));
+            f = 2.0
+                * PI.powi(2 * s)
+                * (1..MESH)
+                    .map(|k| {
+                        let i = (k * k) as f64;
+                        i.powi(s) * a2[k] * (-i * PI * PI * time).exp()
+                    })
+                    .sum::<f64>();
+        }
+        t - (2.0 * n_eff * PI.sqrt() * f).powf(-0.4)
+    };
+
+    // Small samples can put the root beyond Botev's default bracket of 0.1, so
+    // widen the upper end until the sign changes.
+    let mut lo = 1e-12;
+    let mut f_lo = fixed_point(lo);
+    let mut hi = 0.1;
+    while fixed_point(hi).signum() == f_lo.signum() {
+        hi *= 2.0;
+        if hi > 1.0 {
+            return None;
+        }
+    }
+    if !f_lo.is_finite() || !fixed_point(hi).is_finite() {
+        return None;
+    }
+    for _ in 0..100 {
+        let mid = 0.5 * (lo + hi);
+        let f_mid = fixed_point(mid);
+        if !f_mid.is_finite() {
+            return None;
+        }
+        if f_mid.signum() == f_lo.signum() {
+            lo = mid;
+            f_lo = f_mid;
+        } else {
+            hi = mid;
+        }
+    }
+    let bandwidth = (0.5 * (lo + hi)).sqrt() * span;
+    (bandwidth.is_finite() && bandwidth > 0.0).then_some(bandwidth)
+}
+
+/// Maximise the weighted leave-one-out log-likelihood over the bandwidth using a
+/// golden-section search in log space around the Silverman reference.
+fn likelihood_cv_bandwidth(prices: &[f64], weights: &[f64], reference: f64) -> f64 {
+    let score = |log_bw: f64| -> f64 {
+        let bandwidth = log_bw.exp();
+        let norm = 1.0 / (bandwidth * (2.0 * PI).sqrt());
+        let total_weight: f64 = weights.iter().sum();
+        let mut log_likelihood = 0.0;
+        for (i, (xi, wi)) in prices.iter().zip(weights.iter()).enumerate() {
+            let mut sum = 0.0;
+            for (j, (xj, wj)) in prices.iter().zip(weights.iter()).enumerate() {
+                if i != j {
+                    let z = (xi - xj) / bandwidth;
+                    sum += wj * (-0.5 * z * z).exp();
+                }
+            }
+            let denominator = (total_weight - wi).max(1e-12);
+            log_likelihood += wi * (norm * sum / denominator).max(1e-300).ln();
+        }
+        log_likelihood
+    };
+
+    let ratio = (5.0_f64.sqrt() - 1.0) / 2.0;
+    let mut a = (reference / 10.0).ln();
+    let mut b = (reference * 4.0).ln();
+    let mut c = b - ratio * (b - a);
+    let mut d = a + ratio * (b - a);
+    let mut score_c = score(c);
+    let mut score_d = score(d);
+    for _ in 0..40 {
+        if score_c > score_d {
+            b = d;
+            d = c;
+            score_d = score_c;
+            c = b - ratio * (b - a);
+            score_c = score(c);
+        } else {
+            a = c;
+            c = d;
+            score_c = score_d;
+            d = a + ratio * (b - a);
+            score_d = score(d);
+        }
+    }
+    (0.5 * (a + b)).exp()
+}
+
+/// Per-swing multipliers on the global bandwidth. All ones unless an adaptive
+/// mode or ATR scaling is enabled; adaptive factors are normalised to a
+/// geometric mean of one so the global bandwidth keeps its meaning.
+fn local_bandwidth_factors(
+    settings: &DensitySettings,
+    prices: &[f64],
+    weights: &[f64],
+    swing_atrs: &[f64],
+    pilot_bandwidth: f64,
+    total_weight: f64,
+) -> Vec<f64> {
+    let n = prices.len();
+    let mut factors = match settings.adaptive {
+        AdaptiveMode::None => vec![1.0; n],
+        AdaptiveMode::Abramson => {
+            let pilot: Vec<f64> = pilot_density(prices, weights, pilot_bandwidth, total_weight)
+                .into_iter()
+                .map(|density| density.max(1e-300))
+                .collect();
+            let log_geo_mean = pilot
+                .iter()
+                .zip(weights.iter())
+                .map(|(density, weight)| weight * density.ln())
+                .sum::<f64>()
+                / total_weight;
+            let geo_mean = log_geo_mean.exp();
+            pilot
+                .iter()
+                .map(|density| (density / geo_mean).powf(-0.5))
+                .collect()
+        }
+        AdaptiveMode::Knn => {
+            let k = ((n as f64).sqrt().round() as usize).clamp(1, n - 1);
+            let distances: Vec<f64> = kth_neighbour_distances(prices, k)
+                .into_iter()
+                .map(|distance| distance.max(pilot_bandwidth * 0.05))
+                .collect();
+            let log_geo_mean = distances
+                .iter()
+                .zip(weights.iter())
+                .map(|(distance, weight)| weight * distance.ln())
+                .sum::<f64>()
+                / total_weight;
+            let geo_mean = log_geo_mean.exp();
+            distances
+                .iter()
+                .map(|distance| distance / geo_mean)
+                .collect()
+        }
+    };
+
+    if settings.atr_scaled_kernels {
+        let valid: Vec<f64> = swing_atrs
+            .iter()
+            .copied()
+            .filter(|atr| atr.is_finite() && *atr > 0.0)
+            .collect();
+        if !valid.is_empty() {
+            let mean_atr = valid.iter().sum::<f64>() / valid.len() as f64;
+            for (factor, atr) in factors.iter_mut().zip(swing_atrs.iter()) {
+                if atr.is_finite() && *atr > 0.0 {
+                    *factor *= atr / mean_atr;
+                }
+            }
+        }
+    }
+
+    for factor in &mut factors {
+        *factor = if factor.is_finite() {
+            factor.clamp(0.2, 5.0)
+        } else {
+            1.0
+        };
+    }
+    factors
+}
+
+/// Gaussian pilot density at each swing, from a binned estimate on a grid
+/// with a step of an eighth of the pilot bandwidth (capped at
+/// `PILOT_GRID_LIMIT` points), linearly interpolated at the swing prices.
+fn pilot_density(prices: &[f64], weights: &[f64], bandwidth: f64, total_weight: f64) -> Vec<f64> {
+    let low = prices.iter().copied().fold(f64::MAX, f64::min);
+    let high = prices.iter().copied().fold(f64::MIN, f64::max);
+    let span = (high - low).max(bandwidth);
+    let grid_points = ((span / (bandwidth * 0.125)).ceil() as usize + 1).clamp(3, PILOT_GRID_LIMIT);
+    let step = span / (grid_points - 1) as f64;
+    let grid = binned_density(
+        Kernel::Gaussian,
+        prices,
+        weights,
+        bandwidth,
+        total_weight,
+        low,
+        step,
+        grid_points,
+    );
+    prices
+        .iter()
+        .map(|price| {
+            let position = ((price - low) / step).clamp(0.0, (grid_points - 1) as f64);
+            let left = (position.floor() as usize).min(grid_points - 2);
+            let fraction = position - left as f64;
+            grid[left] * (1.0 - fraction) + grid[left + 1] * fraction
+        })
+        .collect()
+}
+
+/// Distance from each swing to its `k`-th nearest other swing, found by
+/// walking outwards from its position in price order.
+fn kth_neighbour_distances(prices: &[f64], k: usize) -> Vec<f64> {
+    let mut order: Vec<usize> = (0..prices.len()).collect();
+    order.sort_by(|&a, &b| {
+        prices[a]
+            .partial_cmp(&prices[b])
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    let sorted: Vec<f64> = order.iter().map(|&idx| prices[idx]).collect();
+    let mut distances = vec![0.0; prices.len()];
+    for (position, &idx) in order.iter().enumerate() {
+        let price = sorted[position];
+        let (mut left, mut right) = (position, position + 1);
+        let mut distance = 0.0;
+        for _ in 0..k {
+            let below = (left > 0).then(|| price - sorted[left - 1]);
+            let above = sorted.get(right).map(|other| other - price);
+            distance = match (below, above) {
+                (Some(below), Some(above)) if below <= above => {
+                    left -= 1;
+                    below
+                }
+                (_, Some(above)) => {
+                    right += 1;
+                    above
+                }
+                (Some(below), None) => {
+                    left -= 1;
+                    below
+                }
+                (None, None) => break,
+            };
+        }
+        distances[idx] = distance;
+    }
+    distances
+}
+
+fn describe_adaptation(settings: &DensitySettings, label: String, factors: &[f64]) -> String {
+    if settings.adaptive == AdaptiveMode::None && !settings.atr_scaled_kernels {
+        return label;
+    }
+    let low = factors.iter().copied().fold(f64::MAX, f64::min);
+    let high = factors.iter().copied().fold(f64::MIN, f64::max);
+    let mut parts = Vec::new();
+    if settings.adaptive != AdaptiveMode::None {
+        parts.push(settings.adaptive.label());
+    }
+    if settings.atr_scaled_kernels {
+        parts.push("atr-scaled");
+    }
+    format!("{label} [{} x{low:.2}-{high:.2}]", parts.join(" + "))
+}
+
+/// Weighted kernel sum where swing `i` uses bandwidth `bandwidth * factors[i]`.
+fn kernel_sum(
+    kernel: Kernel,
     price: f64,
     points: &[f64],
     weights: &[f64],
+    factors: &[f64],
     bandwidth: f64,
     total_weight: f64,
 ) -> f64 {
     if bandwidth <= 0.0 {
         return 0.0;
     }
-    let norm = 1.0 / (total_weight * bandwidth * (2.0 * PI).sqrt());
     let mut sum = 0.0;
-    for (point, weight) in points.iter().zip(weights.iter()) {
-        let z = (price - point) / bandwidth;
-        sum += weight * (-0.5 * z * z).exp();
+    for ((point, weight), factor) in points.iter().zip(weights.iter()).zip(factors.iter()) {
+        let local = bandwidth * factor;
+        sum += weight * kernel.eval((price - point) / local) / local;
     }
-    norm * sum
+    sum / total_weight
 }
@@ -1,133 +1,939 @@
 use std::cmp::Ordering;
 
-use crate::data::{Bar, Level, LevelType, PerformanceStats};
+use clap::ValueEnum;
+
+use statrs::distribution::{ContinuousCDF, Normal};
+use thiserror::Error;
+
+use crate::analysis::gev::{fit_gev, GevFit};
+use crate::analysis::gpd::{
+    anderson_darling, cramer_von_mises, fit_gpd, gpd_return_level, GpdEstimator, GpdFit,
+    ReturnLevelInterval,
+};
+use crate::analysis::sessions::{split_periods, split_sessions, CalendarPeriod};
+use crate::data::{Bar, Level, LevelSource};
+
+/// Tail of the price distribution being projected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TailSide {
+    /// Highs and upside excursions, projecting resistance.
+    Upper,
+    /// Lows and downside excursions, projecting support.
+    Lower,
+}
+
+impl TailSide {
+    pub fn label(&self) -> &'static str {
+        match self {
+            TailSide::Upper => "resistance",
+            TailSide::Lower => "support",
+        }
+    }
+}
+
+/// Observations the tail model is fitted to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum EvtSample {
+    /// Every bar's high (low for supports), pooled without regard to time order.
+    Highs,
+    /// Excursions of each bar's high above a reference price, or of its low
+    /// below it for supports.
+    Excursions,
+}
+
+impl EvtSample {
+    pub fn label(&self) -> &'static str {
+        match self {
+            EvtSample::Highs => "bar highs",
+            EvtSample::Excursions => "excursions",
+        }
+    }
+}
+
+/// Price an excursion is measured from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ExcursionReference {
+    /// Open of the bar's session.
+    SessionOpen,
+    /// Last close of the previous session.
+    PriorClose,
+    /// Highest high (lowest low for supports) of the preceding rolling window.
+    RollingHigh,
+}
+
+impl ExcursionReference {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ExcursionReference::SessionOpen => "session open",
+            ExcursionReference::PriorClose => "prior close",
+            ExcursionReference::RollingHigh => "rolling high",
+        }
+    }
+}
+
+/// How dependent excursions are reduced to roughly independent peaks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Declustering {
+    /// One observation per session: its largest excursion.
+    SessionMaxima,
+    /// Exceedances separated by at most `run_length` bars form one cluster,
+    /// represented by its peak.
+    Runs,
+}
+
+impl Declustering {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Declustering::SessionMaxima => "session maxima",
+            Declustering::Runs => "runs",
+        }
+    }
+}
+
+/// Fewest bars an EVT fit is attempted on.
+const MIN_BARS: usize = 50;
+/// Fitted shapes outside `(MIN_SHAPE, MAX_SHAPE)` are rejected: at -1 the
+/// GPD collapses onto a uniform with the sample maximum as endpoint, and
+/// from 1 on its mean is infinite.
+const MIN_SHAPE: f64 = -0.999;
+const MAX_SHAPE: f64 = 1.0;
+/// Fewest threshold excesses a tail fit is attempted with.
+const MIN_EXCEEDANCES: usize = 5;
+/// Sample quantiles tried as thresholds by automatic selection.
+const CANDIDATE_QUANTILES: [f64; 10] = [0.5, 0.6, 0.7, 0.75, 0.8, 0.85, 0.9, 0.925, 0.95, 0.975];
+
+/// How the exceedance threshold is chosen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ThresholdSelection {
+    /// The configured threshold quantile.
+    Fixed,
+    /// The lowest candidate quantile above which the fitted shape is stable.
+    Auto,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct EvtSettings {
+    /// Quantile (0-1) of the sample used as the exceedance threshold.
+    pub threshold_quantile: f64,
+    pub threshold_selection: ThresholdSelection,
+    pub estimator: GpdEstimator,
+    pub interval: ReturnLevelInterval,
+    /// Coverage of the return-level confidence intervals.
+    pub ci_level: f64,
+    /// Half-width used for levels without an interval.
+    pub band: f64,
+    pub sample: EvtSample,
+    pub reference: ExcursionReference,
+    pub declustering: Declustering,
+    /// Largest gap in bars between exceedances of one runs cluster.
+    pub run_length: usize,
+    /// Bars in the window behind the rolling-high reference.
+    pub rolling_window: usize,
+}
+
+/// Threshold model behind the EVT levels.
+#[derive(Debug, Clone)]
+pub struct EvtFitSummary {
+    pub side: TailSide,
+    pub sample: EvtSample,
+    /// Reference and declustering of an excursion model.
+    pub excursion: Option<(ExcursionReference, Declustering)>,
+    /// Price return levels are added to: zero for bar highs, the current
+    /// value of the reference for excursions.
+    pub anchor: f64,
+    /// Threshold price for bar extremes, or excursion size for excursions.
+    pub threshold: f64,
+    pub threshold_quantile: f64,
+    pub exceedances: usize,
+    pub sample_size: usize,
+    /// What one observation of the fitted sample is.
+    pub unit: &'static str,
+    /// Intervals estimate of the extremal index of bar-level exceedances;
+    /// 1 means isolated extremes, small values mean heavy clustering.
+    pub extremal_index: Option<f64>,
+    pub fit: GpdFit,
+}
+
+/// Threshold-choice diagnostics for one candidate quantile.
+#[derive(Debug, Clone)]
+pub struct ThresholdDiagnostic {
+    pub quantile: f64,
+    /// Threshold on the same scale as `EvtFitSummary::threshold`.
+    pub threshold: f64,
+    pub exceedances: usize,
+    /// Mean excess over the threshold; roughly linear in the threshold
+    /// where the GPD holds (mean residual life).
+    pub mean_excess: f64,
+    pub shape: Option<f64>,
+    pub shape_se: Option<f64>,
+    /// Scale reparameterised as `scale - shape * (threshold - lowest
+    /// candidate threshold)`, constant above a valid threshold.
+    pub modified_scale: Option<f64>,
+    pub anderson_darling: Option<f64>,
+    pub cramer_von_mises: Option<f64>,
+    /// Every higher candidate's shape lies inside this one's shape interval.
+    pub stable: bool,
+    pub selected: bool,
+}
+
+/// Why an EVT fit produced no levels.
+#[derive(Debug, Clone, Error)]
+pub enum EvtFailure {
+    #[error("only {bars} bars in the EVT window, need {required}")]
+    InsufficientData { bars: usize, required: usize },
+
+    #[error("too few exceedances over the threshold ({found}, need {required})")]
+    TooFewExceedances { found: usize, required: usize },
+
+    #[error("GPD fit did not converge")]
+    FitFailed,
+
+    #[error("fitted shape {shape:.3} is outside the usable range (-1, 1)")]
+    InvalidShape { shape: f64 },
+
+    #[error("all {projections} return levels lie on the wrong side of the current price")]
+    ProjectionBehindPrice { projections: usize },
+
+    #[error("no tail probability is rarer than the exceedance rate")]
+    NoReturnLevel,
+}
+
+/// Non-EVT projection used in place of a failed EVT fit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum EvtFallback {
+    /// Emit nothing; only report the failure.
+    None,
+    /// A multiple of the average session true range from the current price.
+    Atr,
+    /// Lognormal expected move from session close-to-close volatility.
+    VolCone,
+}
+
+impl EvtFallback {
+    pub fn label(&self) -> &'static str {
+        match self {
+            EvtFallback::None => "none",
+            EvtFallback::Atr => "session ATR",
+            EvtFallback::VolCone => "vol cone",
+        }
+    }
+}
+
+pub struct EvtAnalysis {
+    pub fit: Option<EvtFitSummary>,
+    /// Set when no level was produced.
+    pub failure: Option<EvtFailure>,
+    pub levels: Vec<Level>,
+    /// Candidate diagnostics, filled by automatic threshold selection.
+    pub thresholds: Vec<ThresholdDiagnostic>,
+}
+
+/// Sample prepared for a peaks-over-threshold fit.
+struct TailSample {
+    anchor: f64,
+    /// Quantile the threshold was taken at, which session maxima may lower.
+    quantile: f64,
+    threshold: f64,
+    excesses: Vec<f64>,
+    /// Rate of independent exceedances per observation.
+    rate: f64,
+    sample_size: usize,
+    unit: &'static str,
+    /// Observations per session, converting a per-session tail probability
+    /// into a per-observation one.
+    per_session: f64,
+    extremal_index: Option<f64>,
+}
 
 /// Compute EVT-based resistance projections using a peaks-over-threshold model.
+///
+/// Each projected resistance is a GPD return level whose zone is its
+/// confidence interval. Levels whose interval could not be computed fall
+/// back to the flat band and say so in their label.
+///
+/// With bar highs the tail probabilities are per bar. With excursions they
+/// are per session: `p` is the chance that a session's largest excursion
+/// above the reference stays below the level, and the excursion return level
+/// is added to the reference as it stands at the last bar.
 pub fn compute_evt_resistances(
     bars: &[Bar],
     tail_probs: &[f64],
-    threshold_quantile: f64,
-    confidence_band: f64,
+    settings: &EvtSettings,
+    current_price: f64,
+) -> EvtAnalysis {
+    project_upper_tail(bars, tail_probs, settings, current_price)
+}
+
+/// Compute EVT-based support projections from the lower tail.
+///
+/// Prices are mirrored so that lows become highs, the upper-tail model is
+/// fitted exactly as for resistances, and the projections are mirrored back
+/// below the current price. Excursions are measured downward from the same
+/// references, with the rolling high becoming a rolling low.
+pub fn compute_evt_supports(
+    bars: &[Bar],
+    tail_probs: &[f64],
+    settings: &EvtSettings,
     current_price: f64,
-) -> Vec<Level> {
-    if bars.len() < 50 || tail_probs.is_empty() {
-        return Vec::new();
+) -> EvtAnalysis {
+    let mirrored: Vec<Bar> = bars
+        .iter()
+        .map(|bar| Bar {
+            timestamp: bar.timestamp,
+            open: -bar.open,
+            high: -bar.low,
+            low: -bar.high,
+            close: -bar.close,
+            volume: bar.volume,
+        })
+        .collect();
+    let analysis = project_upper_tail(&mirrored, tail_probs, settings, -current_price);
+    EvtAnalysis {
+        fit: analysis.fit.map(|mut summary| {
+            summary.side = TailSide::Lower;
+            summary.anchor = -summary.anchor;
+            if summary.sample == EvtSample::Highs {
+                summary.threshold = -summary.threshold;
+            }
+            summary
+        }),
+        failure: analysis.failure,
+        thresholds: analysis
+            .thresholds
+            .into_iter()
+            .map(|mut diagnostic| {
+                if settings.sample == EvtSample::Highs {
+                    diagnostic.threshold = -diagnostic.threshold;
+                }
+                diagnostic
+            })
+            .collect(),
+        levels: analysis
+            .levels
+            .into_iter()
+            .map(|level| {
+                Level::new(
+                    -level.price,
+                    (-level.zone_high, -level.zone_low),
+                    level.confidence,
+                    level.source,
+                    level.label,
+                    current_price,
+                )
+            })
+            .collect(),
+    }
+}
+
+fn project_upper_tail(
+    bars: &[Bar],
+    tail_probs: &[f64],
+    settings: &EvtSettings,
+    current_price: f64,
+) -> EvtAnalysis {
+    let mut analysis = EvtAnalysis {
+        fit: None,
+        failure: None,
+        levels: Vec::new(),
+        thresholds: Vec::new(),
+    };
+    if bars.len() < MIN_BARS {
+        analysis.failure = Some(EvtFailure::InsufficientData {
+            bars: bars.len(),
+            required: MIN_BARS,
+        });
+        return analysis;
+    }
+    let threshold_quantile = match settings.threshold_selection {
+        ThresholdSelection::Fixed => settings.threshold_quantile,
+        ThresholdSelection::Auto => {
+            analysis.thresholds = threshold_diagnostics(bars, settings);
+            analysis
+                .thresholds
+                .iter()
+                .find(|diagnostic| diagnostic.selected)
+                .map_or(settings.threshold_quantile, |diagnostic| {
+                    diagnostic.quantile
+                })
+        }
+    };
+    let sample = match build_sample(bars, settings, threshold_quantile) {
+        Some(sample) if sample.excesses.len() >= MIN_EXCEEDANCES => sample,
+        sample => {
+            analysis.failure = Some(EvtFailure::TooFewExceedances {
+                found: sample.map_or(0, |sample| sample.excesses.len()),
+                required: MIN_EXCEEDANCES,
+            });
+            return analysis;
+        }
+    };
+    let Some(fit) = fit_sample(&sample, settings.estimator) else {
+        analysis.failure = Some(EvtFailure::FitFailed);
+        return analysis;
+    };
+    if !(fit.shape > MIN_SHAPE && fit.shape < MAX_SHAPE) {
+        analysis.failure = Some(EvtFailure::InvalidShape { shape: fit.shape });
+        return analysis;
     }
 
-    let mut highs: Vec<f64> = bars.iter().map(|bar| bar.high).collect();
-    highs.sort_by(|a, b| match (a.is_finite(), b.is_finite()) {
-        (true, true) => a.partial_cmp(b).unwrap_or(Ordering::Equal),
-        (false, true) => Ordering::Less,
-        (true, false) => Ordering::Greater,
-        (false, false) => Ordering::Equal,
+    let mut behind_price = 0;
+    for &p in tail_probs {
+        if !(0.0..1.0).contains(&p) {
+            continue;
+        }
+        let Some(level) = gpd_return_level(
+            &fit,
+            sample.threshold,
+            sample.rate,
+            sample.sample_size,
+            (1.0 - p) / sample.per_session,
+            settings.interval,
+            settings.ci_level,
+        ) else {
+            continue;
+        };
+        let projected = sample.anchor + level.level;
+        if !projected.is_finite() || level.level <= sample.threshold {
+            continue;
+        }
+        if projected <= current_price {
+            behind_price += 1;
+            continue;
+        }
+        let (zone, note) = match level.bounds {
+            Some((low, high)) if level.upper_bounded => {
+                ((sample.anchor + low, sample.anchor + high), String::new())
+            }
+            Some((low, high)) => (
+                (sample.anchor + low, sample.anchor + high),
+                " CI open".to_string(),
+            ),
+            None => (
+                (projected - settings.band, projected + settings.band),
+                " no CI".to_string(),
+            ),
+        };
+        // PWM fits have no likelihood to profile, so say when the
+        // requested interval was replaced by the delta method.
+        let note = if level.bounds.is_some() && level.interval != settings.interval {
+            format!("{note} {} CI", level.interval.label())
+        } else {
+            note
+        };
+        analysis.levels.push(Level::new(
+            projected,
+            zone,
+            p.clamp(0.0, 1.0),
+            LevelSource::Evt,
+            format!("p={p:.4}{note}"),
+            current_price,
+        ));
+    }
+    analysis.fit = Some(EvtFitSummary {
+        side: TailSide::Upper,
+        sample: settings.sample,
+        excursion: (settings.sample == EvtSample::Excursions)
+            .then_some((settings.reference, settings.declustering)),
+        anchor: sample.anchor,
+        threshold: sample.threshold,
+        threshold_quantile: sample.quantile,
+        exceedances: sample.excesses.len(),
+        sample_size: sample.sample_size,
+        unit: sample.unit,
+        extremal_index: sample.extremal_index,
+        fit,
     });
 
-    let n = highs.len();
-    let threshold_idx = ((n as f64 * threshold_quantile).floor() as usize).clamp(0, n - 1);
-    let threshold = highs[threshold_idx];
-    let max_high = *highs.last().unwrap_or(&threshold);
-    let exceedances: Vec<f64> = highs
+    if analysis.levels.is_empty() {
+        analysis.failure = Some(if behind_price > 0 {
+            EvtFailure::ProjectionBehindPrice {
+                projections: behind_price,
+            }
+        } else {
+            EvtFailure::NoReturnLevel
+        });
+    }
+
+    analysis.levels.sort_by(|a, b| {
+        b.confidence
+            .partial_cmp(&a.confidence)
+            .unwrap_or(Ordering::Equal)
+    });
+    analysis
+}
+
+fn build_sample(
+    bars: &[Bar],
+    settings: &EvtSettings,
+    threshold_quantile: f64,
+) -> Option<TailSample> {
+    match settings.sample {
+        EvtSample::Highs => Some(highs_sample(bars, threshold_quantile)),
+        EvtSample::Excursions => excursion_sample(bars, settings, threshold_quantile),
+    }
+}
+
+fn fit_sample(sample: &TailSample, estimator: GpdEstimator) -> Option<GpdFit> {
+    if sample.excesses.len() >= MIN_EXCEEDANCES && sample.rate > 0.0 {
+        fit_gpd(&sample.excesses, estimator)
+    } else {
+        None
+    }
+}
+
+/// Fit every candidate threshold and mark the one to use.
+///
+/// A candidate is stable when the shape fitted at each higher candidate
+/// falls inside its own shape interval at `ci_level`, the parameter-
+/// stability criterion read off a threshold-stability plot. The lowest
+/// stable candidate is selected, keeping as many excesses as the GPD
+/// allows. When none is stable the candidate with the smallest
+/// Anderson-Darling statistic is used instead.
+fn threshold_diagnostics(bars: &[Bar], settings: &EvtSettings) -> Vec<ThresholdDiagnostic> {
+    let samples: Vec<TailSample> = CANDIDATE_QUANTILES
+        .iter()
+        .filter_map(|&quantile| build_sample(bars, settings, quantile))
+        .collect();
+    let origin = samples.first().map_or(0.0, |sample| sample.threshold);
+    let mut diagnostics: Vec<ThresholdDiagnostic> = samples
+        .into_iter()
+        .map(|sample| {
+            let fit = fit_sample(&sample, settings.estimator);
+            ThresholdDiagnostic {
+                quantile: sample.quantile,
+                threshold: sample.threshold,
+                exceedances: sample.excesses.len(),
+                mean_excess: sample.excesses.iter().sum::<f64>()
+                    / sample.excesses.len().max(1) as f64,
+                shape: fit.as_ref().map(|fit| fit.shape),
+                shape_se: fit.as_ref().and_then(GpdFit::shape_se),
+                modified_scale: fit
+                    .as_ref()
+                    .map(|fit| fit.scale - fit.shape * (sample.threshold - origin)),
+                anderson_darling: fit.as_ref().map(anderson_darling),
+                cramer_von_mises: fit.as_ref().map(cramer_von_mises),
+                stable: false,
+                selected: false,
+            }
+        })
+        .collect();
+
+    let z = Normal::new(0.0, 1.0)
+        .map(|normal| normal.inverse_cdf(0.5 + 0.5 * settings.ci_level.clamp(0.0, 0.999_999)))
+        .unwrap_or(1.96);
+    for idx in 0..diagnostics.len() {
+        let (Some(shape), Some(se)) = (diagnostics[idx].shape, diagnostics[idx].shape_se) else {
+            continue;
+        };
+        let higher: Vec<f64> = diagnostics[idx + 1..]
+            .iter()
+            .filter_map(|diagnostic| diagnostic.shape)
+            .collect();
+        diagnostics[idx].stable =
+            !higher.is_empty() && higher.iter().all(|other| (other - shape).abs() <= z * se);
+    }
+
+    let chosen = diagnostics
+        .iter()
+        .position(|diagnostic| diagnostic.stable)
+        .or_else(|| {
+            diagnostics
+                .iter()
+                .enumerate()
+                .filter_map(|(idx, diagnostic)| diagnostic.anderson_darling.map(|ad| (idx, ad)))
+                .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal))
+                .map(|(idx, _)| idx)
+        });
+    if let Some(idx) = chosen {
+        diagnostics[idx].selected = true;
+    }
+    diagnostics
+}
+
+/// Every bar high above the quantile threshold, treated as independent.
+fn highs_sample(bars: &[Bar], threshold_quantile: f64) -> TailSample {
+    let highs: Vec<f64> = bars.iter().map(|bar| bar.high).collect();
+    let threshold = quantile(&highs, threshold_quantile);
+    let excesses: Vec<f64> = highs
         .iter()
         .filter(|&&value| value > threshold)
         .map(|&value| value - threshold)
         .collect();
-    let nu = exceedances.len();
-    if nu < 5 {
-        return Vec::new();
+    TailSample {
+        anchor: 0.0,
+        quantile: threshold_quantile,
+        threshold,
+        rate: excesses.len() as f64 / highs.len() as f64,
+        excesses,
+        sample_size: highs.len(),
+        unit: "bars",
+        per_session: 1.0,
+        extremal_index: extremal_index(&highs, threshold),
     }
+}
 
-    let lambda = nu as f64 / n as f64;
-    if lambda <= 0.0 {
-        return Vec::new();
+/// Declustered excursions above the configured reference.
+///
+/// A lookback of a few weeks has only as many session maxima as sessions, so
+/// their threshold quantile is lowered until `MIN_EXCEEDANCES` maxima lie
+/// above it. Runs never continue across a session boundary, since the gap
+/// between sessions is not a run of quiet bars.
+fn excursion_sample(
+    bars: &[Bar],
+    settings: &EvtSettings,
+    threshold_quantile: f64,
+) -> Option<TailSample> {
+    let sessions = split_sessions(bars);
+    let mut references: Vec<Option<f64>> = vec![None; bars.len()];
+    match settings.reference {
+        ExcursionReference::SessionOpen => {
+            for session in &sessions {
+                let open = bars[session.range.start].open;
+                for reference in &mut references[session.range.clone()] {
+                    *reference = Some(open);
+                }
+            }
+        }
+        ExcursionReference::PriorClose => {
+            for pair in sessions.windows(2) {
+                let close = bars[pair[0].range.end - 1].close;
+                for reference in &mut references[pair[1].range.clone()] {
+                    *reference = Some(close);
+                }
+            }
+        }
+        ExcursionReference::RollingHigh => {
+            let window = settings.rolling_window.max(1);
+            for idx in window..bars.len() {
+                references[idx] = Some(
+                    bars[idx - window..idx]
+                        .iter()
+                        .map(|bar| bar.high)
+                        .fold(f64::MIN, f64::max),
+                );
+            }
+        }
     }
 
-    let mean_excess: f64 = exceedances.iter().sum::<f64>() / nu as f64;
-    let variance: f64 = exceedances
+    // Reference in force for the next move from the last bar.
+    let anchor = match settings.reference {
+        ExcursionReference::SessionOpen => bars[sessions.last()?.range.start].open,
+        ExcursionReference::PriorClose => {
+            let prior = &sessions[sessions.len().checked_sub(2)?];
+            bars[prior.range.end - 1].close
+        }
+        ExcursionReference::RollingHigh => {
+            let window = settings.rolling_window.max(1).min(bars.len());
+            bars[bars.len() - window..]
+                .iter()
+                .map(|bar| bar.high)
+                .fold(f64::MIN, f64::max)
+        }
+    };
+
+    let excursions: Vec<(usize, f64)> = bars
         .iter()
-        .map(|x| (x - mean_excess).powi(2))
-        .sum::<f64>()
-        / (nu as f64 - 1.0).max(1.0);
-
-    let (shape, scale) = if variance > 0.0 {
-        let ratio = mean_excess * mean_excess / variance;
-        let mut xi = 0.5 * (1.0 - ratio);
-        if !xi.is_finite() {
-            xi = 0.0;
-        }
-        let mut sigma = mean_excess * (1.0 - xi);
-        if !sigma.is_finite() || sigma <= 0.0 {
-            xi = 0.0;
-            sigma = mean_excess.max(1e-6);
-        }
-        (xi, sigma)
+        .zip(&references)
+        .enumerate()
+        .filter_map(|(idx, (bar, reference))| reference.map(|r| (idx, bar.high - r)))
+        .collect();
+    if excursions.len() < 2 {
+        return None;
+    }
+    let values: Vec<f64> = excursions.iter().map(|&(_, value)| value).collect();
+
+    match settings.declustering {
+        Declustering::SessionMaxima => {
+            let maxima: Vec<f64> = sessions
+                .iter()
+                .filter_map(|session| {
+                    excursions
+                        .iter()
+                        .filter(|(idx, _)| session.range.contains(idx))
+                        .map(|&(_, value)| value)
+                        .reduce(f64::max)
+                })
+                .collect();
+            let maxima_quantile = threshold_quantile.min(
+                ((maxima.len().saturating_sub(MIN_EXCEEDANCES)) as f64 - 0.5).max(0.0)
+                    / maxima.len().max(1) as f64,
+            );
+            let threshold = quantile(&maxima, maxima_quantile);
+            let excesses: Vec<f64> = maxima
+                .iter()
+                .filter(|&&value| value > threshold)
+                .map(|&value| value - threshold)
+                .collect();
+            let bar_threshold = quantile(&values, threshold_quantile);
+            Some(TailSample {
+                anchor,
+                quantile: maxima_quantile,
+                threshold,
+                rate: excesses.len() as f64 / maxima.len() as f64,
+                excesses,
+                sample_size: maxima.len(),
+                unit: "sessions",
+                per_session: 1.0,
+                extremal_index: extremal_index(&values, bar_threshold),
+            })
+        }
+        Declustering::Runs => {
+            let threshold = quantile(&values, threshold_quantile);
+            let mut session_of = vec![0; bars.len()];
+            for (number, session) in sessions.iter().enumerate() {
+                for slot in &mut session_of[session.range.clone()] {
+                    *slot = number;
+                }
+            }
+            let mut clusters: Vec<(usize, f64)> = Vec::new();
+            for &(idx, value) in excursions.iter().filter(|(_, value)| *value > threshold) {
+                match clusters.last_mut() {
+                    Some((last, peak))
+                        if session_of[*last] == session_of[idx]
+                            && idx - *last <= settings.run_length.max(1) =>
+                    {
+                        *last = idx;
+                        *peak = peak.max(value);
+                    }
+                    _ => clusters.push((idx, value)),
+                }
+            }
+            let active_sessions = sessions
+                .iter()
+                .filter(|session| {
+                    references[session.range.clone()]
+                        .iter()
+                        .any(Option::is_some)
+                })
+                .count()
+                .max(1);
+            let theta = extremal_index(&values, threshold);
+            let exceed_rate = values.iter().filter(|&&value| value > threshold).count() as f64
+                / values.len() as f64;
+            Some(TailSample {
+                anchor,
+                quantile: threshold_quantile,
+                threshold,
+                excesses: clusters.iter().map(|&(_, peak)| peak - threshold).collect(),
+                rate: exceed_rate * theta.unwrap_or(1.0),
+                sample_size: values.len(),
+                unit: "bars",
+                per_session: values.len() as f64 / active_sessions as f64,
+                extremal_index: theta,
+            })
+        }
+    }
+}
+
+/// Ferro-Segers intervals estimator of the extremal index, from the gaps
+/// between successive exceedances of `threshold` in time order.
+fn extremal_index(values: &[f64], threshold: f64) -> Option<f64> {
+    let times: Vec<usize> = values
+        .iter()
+        .enumerate()
+        .filter(|(_, &value)| value > threshold)
+        .map(|(idx, _)| idx)
+        .collect();
+    if times.len() < 3 {
+        return None;
+    }
+    let gaps: Vec<f64> = times.windows(2).map(|w| (w[1] - w[0]) as f64).collect();
+    let count = gaps.len() as f64;
+    let max_gap = gaps.iter().copied().fold(0.0, f64::max);
+    let theta = if max_gap <= 2.0 {
+        let sum: f64 = gaps.iter().sum();
+        let sum_sq: f64 = gaps.iter().map(|gap| gap * gap).sum();
+        2.0 * sum * sum / (count * sum_sq)
     } else {
-        (0.0, mean_excess.max(1e-6))
+        let sum: f64 = gaps.iter().map(|gap| gap - 1.0).sum();
+        let sum_prod: f64 = gaps.iter().map(|gap| (gap - 1.0) * (gap - 2.0)).sum();
+        if sum_prod <= 0.0 {
+            return Some(1.0);
+        }
+        2.0 * sum * sum / (count * sum_prod)
     };
+    theta.is_finite().then(|| theta.clamp(0.0, 1.0))
+}
 
-    let mut levels = Vec::new();
-    for &p in tail_probs {
-        if !(0.0..1.0).contains(&p) {
-            continue;
-        }
-        let tail_prob = 1.0 - p;
-        if tail_prob <= 0.0 || tail_prob >= lambda {
+/// Order statistic at `q` of the finite values.
+fn quantile(values: &[f64], q: f64) -> f64 {
+    let mut sorted: Vec<f64> = values.iter().copied().filter(|v| v.is_finite()).collect();
+    if sorted.is_empty() {
+        return f64::NAN;
+    }
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+    let n = sorted.len();
+    sorted[((n as f64 * q).floor() as usize).clamp(0, n - 1)]
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct GevSettings {
+    /// Coverage of the return-level confidence intervals.
+    pub ci_level: f64,
+    /// Half-width used for return levels without an interval.
+    pub band: f64,
+    /// Fewest completed blocks a fit is attempted with.
+    pub min_blocks: usize,
+}
+
+/// Block-maxima fit for one calendar period and tail.
+#[derive(Debug, Clone)]
+pub struct GevBlockFit {
+    pub period: CalendarPeriod,
+    pub side: TailSide,
+    /// Open of the current block, which return levels are measured from.
+    pub anchor: f64,
+    pub fit: GevFit,
+    pub return_levels: Vec<GevReturnLevel>,
+}
+
+/// Price a block extreme exceeds with the given probability.
+#[derive(Debug, Clone, Copy)]
+pub struct GevReturnLevel {
+    pub probability: f64,
+    pub price: f64,
+    pub bounds: Option<(f64, f64)>,
+}
+
+pub struct GevAnalysis {
+    pub fits: Vec<GevBlockFit>,
+    pub levels: Vec<Level>,
+}
+
+/// Project block extremes with a GEV fitted to session, weekly or monthly
+/// maxima and minima.
+///
+/// Each completed block contributes its high above and its low below its own
+/// open, so the fit describes how far a block travels rather than where
+/// price happened to be. A return level for probability `p` is the move the
+/// block extreme exceeds with probability `p`, added to or subtracted from
+/// the open of the current, still running block: "the session high exceeds
+/// X with 1% probability". Levels already crossed by the current price are
+/// reported in the fit but not emitted.
+pub fn compute_gev_levels(
+    bars: &[Bar],
+    periods: &[CalendarPeriod],
+    probabilities: &[f64],
+    settings: &GevSettings,
+    current_price: f64,
+) -> GevAnalysis {
+    let mut analysis = GevAnalysis {
+        fits: Vec::new(),
+        levels: Vec::new(),
+    };
+    for &period in periods {
+        let blocks = split_periods(bars, period);
+        let Some((current, completed)) = blocks.split_last() else {
             continue;
-        }
-        let ratio = lambda / tail_prob;
-        let mut projected = if shape.abs() <= 1e-6 {
-            threshold + scale * ratio.ln()
-        } else {
-            let inner = ratio.powf(shape);
-            threshold + (scale / shape) * (inner - 1.0)
         };
-        projected = projected.min(max_high + confidence_band.max(1.0) * 5.0);
-        if !projected.is_finite() || projected <= threshold || projected <= current_price {
+        if completed.len() < settings.min_blocks.max(5) {
             continue;
         }
-        let confidence = p.clamp(0.0, 1.0);
-        let mut level = Level {
-            price: projected,
-            density: 0.0,
-            confidence,
-            confidence_band,
-            level_type: LevelType::Resistance,
-            performance: PerformanceStats::empty(),
-            distance_from_last: (projected - current_price).abs(),
-        };
-        if level.confidence_band <= 0.0 {
-            level.confidence_band = (projected.abs() * 0.001).max(1.0);
-        }
-        levels.push(level);
-    }
-
-    if levels.is_empty() {
-        let step = confidence_band.max(1.0);
-        let mut fallback = (max_high + step).min(max_high + step * 5.0);
-        if fallback <= current_price {
-            fallback = (current_price + step).min(max_high + step * 5.0);
-        }
-        if fallback.is_finite() {
-            let confidence = tail_probs.first().copied().unwrap_or(0.99).clamp(0.0, 1.0);
-            let mut level = Level {
-                price: fallback,
-                density: 0.0,
-                confidence,
-                confidence_band,
-                level_type: LevelType::Resistance,
-                performance: PerformanceStats::empty(),
-                distance_from_last: (fallback - current_price).abs(),
+        let anchor = bars[current.range.start].open;
+        for side in [TailSide::Upper, TailSide::Lower] {
+            let extremes: Vec<f64> = completed
+                .iter()
+                .map(|block| {
+                    let block_bars = block.bars(bars);
+                    let open = block_bars[0].open;
+                    match side {
+                        TailSide::Upper => {
+                            block_bars
+                                .iter()
+                                .map(|bar| bar.high)
+                                .fold(f64::MIN, f64::max)
+                                - open
+                        }
+                        TailSide::Lower => {
+                            open - block_bars
+                                .iter()
+                                .map(|bar| bar.low)
+                                .fold(f64::MAX, f64::min)
+                        }
+                    }
+                })
+                .collect();
+            let Some(fit) = fit_gev(&extremes) else {
+                continue;
             };
-            if level.confidence_band <= 0.0 {
-                level.confidence_band = (fallback.abs() * 0.001).max(1.0);
+            let direction = match side {
+                TailSide::Upper => 1.0,
+                TailSide::Lower => -1.0,
+            };
+            let mut return_levels = Vec::new();
+            for &prob in probabilities {
+                let Some((moved, bounds)) = fit.return_level(prob, settings.ci_level) else {
+                    continue;
+                };
+                let price = anchor + direction * moved;
+                let bounds = bounds.map(|(low, high)| {
+                    let (a, b) = (anchor + direction * low, anchor + direction * high);
+                    (a.min(b), a.max(b))
+                });
+                return_levels.push(GevReturnLevel {
+                    probability: prob,
+                    price,
+                    bounds,
+                });
+                let beyond = match side {
+                    TailSide::Upper => price > current_price,
+                    TailSide::Lower => price < current_price,
+                };
+                if beyond {
+                    analysis.levels.push(Level::new(
+                        price,
+                        bounds.unwrap_or((price - settings.band, price + settings.band)),
+                        1.0 - prob,
+                        LevelSource::Evt,
+                        format!(
+                            "GEV {} {} {:.1}%",
+                            period.label(),
+                            match side {
+                                TailSide::Upper => "high",
+                                TailSide::Lower => "low",
+                            },
+                            prob * 100.0
+                        ),
+                        current_price,
+                    ));
+                }
             }
-            levels.push(level);
+            analysis.fits.push(GevBlockFit {
+                period,
+                side,
+                anchor,
+                fit,
+                return_levels,
+            });
+        }
+    }
+    analysis
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exceedances_at(times: &[usize], len: usize) -> Vec<f64> {
+        let mut values = vec![0.0; len];
+        for &time in times {
+            values[time] = 1.0;
         }
+        values
     }
 
-    levels.sort_by(|a, b| {
-        b.confidence
-            .partial_cmp(&a.confidence)
-            .unwrap_or(Ordering::Equal)
-    });
-    levels
+    #[test]
+    fn extremal_index_of_paired_exceedances() {
+        // Gaps 1, 9, 1: sum of (g - 1) is 8 and of (g - 1)(g - 2) is 56.
+        let values = exceedances_at(&[0, 1, 10, 11], 20);
+        let theta = extremal_index(&values, 0.5).unwrap();
+        assert!((theta - 16.0 / 21.0).abs() < 1e-12, "theta {theta}");
+    }
+
+    #[test]
+    fn extremal_index_of_isolated_exceedances_is_one() {
+        let values = exceedances_at(&[0, 5, 10, 15], 20);
+        assert_eq!(extremal_index(&values, 0.5), Some(1.0));
+        assert_eq!(extremal_index(&exceedances_at(&[3, 9], 20), 0.5), None);
+    }
 }
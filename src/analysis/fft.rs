@@ -0,0 +1,191 @@
+use std::f64::consts::PI;
+
+/// In-place iterative radix-2 FFT over split real/imaginary buffers.
+///
+/// The buffer length must be a power of two. The inverse transform is scaled
+/// by `1/n` so that `fft(fft(x), inverse)` returns `x`.
+pub fn fft(re: &mut [f64], im: &mut [f64], inverse: bool) {
+    let n = re.len();
+    debug_assert_eq!(n, im.len());
+    debug_assert!(n.is_power_of_two());
+    if n < 2 {
+        return;
+    }
+
+    let mut j = 0usize;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    let sign = if inverse { 1.0 } else { -1.0 };
+    let mut len = 2;
+    while len <= n {
+        let angle = sign * 2.0 * PI / len as f64;
+        let (w_im, w_re) = angle.sin_cos();
+        for start in (0..n).step_by(len) {
+            let mut cur_re = 1.0;
+            let mut cur_im = 0.0;
+            for k in 0..len / 2 {
+                let a = start + k;
+                let b = a + len / 2;
+                let t_re = re[b] * cur_re - im[b] * cur_im;
+                let t_im = re[b] * cur_im + im[b] * cur_re;
+                re[b] = re[a] - t_re;
+                im[b] = im[a] - t_im;
+                re[a] += t_re;
+                im[a] += t_im;
+                let next_re = cur_re * w_re - cur_im * w_im;
+                cur_im = cur_re * w_im + cur_im * w_re;
+                cur_re = next_re;
+            }
+        }
+        len <<= 1;
+    }
+
+    if inverse {
+        let scale = 1.0 / n as f64;
+        for value in re.iter_mut().chain(im.iter_mut()) {
+            *value *= scale;
+        }
+    }
+}
+
+/// In-place discrete Fourier transform of any length, with the sign and
+/// scaling conventions of `fft`.
+///
+/// Power-of-two lengths go straight to `fft`; others use Bluestein's chirp-z
+/// algorithm, which writes the transform as a convolution with the chirp
+/// `exp(±iπm²/n)` and evaluates that with power-of-two FFTs.
+pub fn dft(re: &mut [f64], im: &mut [f64], inverse: bool) {
+    let n = re.len();
+    debug_assert_eq!(n, im.len());
+    if n < 2 {
+        return;
+    }
+    if n.is_power_of_two() {
+        fft(re, im, inverse);
+        return;
+    }
+
+    let sign = if inverse { 1.0 } else { -1.0 };
+    // m² is reduced mod 2n so the angle stays accurate for long inputs.
+    let chirp: Vec<(f64, f64)> = (0..n)
+        .map(|m| {
+            let (sin, cos) = (sign * PI * ((m * m) % (2 * n)) as f64 / n as f64).sin_cos();
+            (cos, sin)
+        })
+        .collect();
+    let size = (2 * n - 1).next_power_of_two();
+
+    let mut a_re = vec![0.0; size];
+    let mut a_im = vec![0.0; size];
+    for (j, &(cos, sin)) in chirp.iter().enumerate() {
+        a_re[j] = re[j] * cos - im[j] * sin;
+        a_im[j] = re[j] * sin + im[j] * cos;
+    }
+    let mut b_re = vec![0.0; size];
+    let mut b_im = vec![0.0; size];
+    for (m, &(cos, sin)) in chirp.iter().enumerate() {
+        b_re[m] = cos;
+        b_im[m] = -sin;
+        if m > 0 {
+            b_re[size - m] = cos;
+            b_im[size - m] = -sin;
+        }
+    }
+
+    fft(&mut a_re, &mut a_im, false);
+    fft(&mut b_re, &mut b_im, false);
+    for i in 0..size {
+        let product_re = a_re[i] * b_re[i] - a_im[i] * b_im[i];
+        a_im[i] = a_re[i] * b_im[i] + a_im[i] * b_re[i];
+        a_re[i] = product_re;
+    }
+    fft(&mut a_re, &mut a_im, true);
+
+    let scale = if inverse { 1.0 / n as f64 } else { 1.0 };
+    for (k, &(cos, sin)) in chirp.iter().enumerate() {
+        re[k] = (a_re[k] * cos - a_im[k] * sin) * scale;
+        im[k] = (a_re[k] * sin + a_im[k] * cos) * scale;
+    }
+}
+
+/// Linear convolution of `signal` with a symmetric kernel given by its
+/// non-negative lags (`kernel[0]` is the centre tap). The output has the same
+/// length as `signal`.
+pub fn convolve_symmetric(signal: &[f64], kernel: &[f64]) -> Vec<f64> {
+    if signal.is_empty() || kernel.is_empty() {
+        return vec![0.0; signal.len()];
+    }
+    let reach = kernel.len() - 1;
+    let size = (signal.len() + reach + 1).next_power_of_two();
+
+    let mut sig_re = vec![0.0; size];
+    let mut sig_im = vec![0.0; size];
+    sig_re[..signal.len()].copy_from_slice(signal);
+
+    let mut ker_re = vec![0.0; size];
+    let mut ker_im = vec![0.0; size];
+    ker_re[0] = kernel[0];
+    for (lag, &value) in kernel.iter().enumerate().skip(1) {
+        ker_re[lag] = value;
+        ker_re[size - lag] = value;
+    }
+
+    fft(&mut sig_re, &mut sig_im, false);
+    fft(&mut ker_re, &mut ker_im, false);
+    for i in 0..size {
+        let re = sig_re[i] * ker_re[i] - sig_im[i] * ker_im[i];
+        let im = sig_re[i] * ker_im[i] + sig_im[i] * ker_re[i];
+        sig_re[i] = re;
+        sig_im[i] = im;
+    }
+    fft(&mut sig_re, &mut sig_im, true);
+
+    sig_re.truncate(signal.len());
+    sig_re
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dft_matches_hand_computed_length_three() {
+        let mut re = vec![1.0, 2.0, 3.0];
+        let mut im = vec![0.0; 3];
+        dft(&mut re, &mut im, false);
+        let half_root_three = 3.0_f64.sqrt() / 2.0;
+        let expected = [
+            (6.0, 0.0),
+            (-1.5, half_root_three),
+            (-1.5, -half_root_three),
+        ];
+        for (k, &(want_re, want_im)) in expected.iter().enumerate() {
+            assert!((re[k] - want_re).abs() < 1e-9, "re[{k}] = {}", re[k]);
+            assert!((im[k] - want_im).abs() < 1e-9, "im[{k}] = {}", im[k]);
+        }
+    }
+
+    #[test]
+    fn dft_inverse_round_trips_odd_length() {
+        let signal = [0.5, -1.25, 2.0, 0.0, 3.5, -0.75, 1.0];
+        let mut re = signal.to_vec();
+        let mut im = vec![0.0; signal.len()];
+        dft(&mut re, &mut im, false);
+        dft(&mut re, &mut im, true);
+        for (value, original) in re.iter().zip(signal) {
+            assert!((value - original).abs() < 1e-9);
+        }
+        assert!(im.iter().all(|value| value.abs() < 1e-9));
+    }
+}
@@ -0,0 +1,112 @@
+use crate::analysis::swings::largest_legs;
+use crate::data::{Level, LevelSource, SwingPoint};
+
+/// Retracement ratios measured back from the end of a leg.
+const RETRACEMENTS: [f64; 4] = [0.382, 0.5, 0.618, 0.786];
+/// Extension ratios projected from the start of a leg past its end.
+const EXTENSIONS: [f64; 3] = [1.272, 1.618, 2.0];
+
+#[derive(Debug, Clone, Copy)]
+pub struct FibonacciSettings {
+    /// Number of largest swing legs to project from.
+    pub legs: usize,
+    /// Distance within which projections from different legs are clustered.
+    pub confluence_tolerance: f64,
+    /// Minimum number of distinct legs a cluster needs to be emitted.
+    pub min_confluence: usize,
+    /// Half-width of the zone added around each cluster.
+    pub band: f64,
+}
+
+/// One retracement or extension price projected from a ranked leg.
+#[derive(Debug, Clone, Copy)]
+struct Projection {
+    price: f64,
+    ratio: f64,
+    /// Rank of the leg by size, 1 for the largest.
+    leg: usize,
+}
+
+/// Fibonacci retracements and extensions of the largest swing legs, merged
+/// into confluence clusters.
+///
+/// Projections closer than the tolerance to their neighbour chain into one
+/// cluster, emitted at the mean price with a zone spanning its members. The
+/// confidence is the share of projected legs that contribute to the cluster,
+/// so a level supported by every leg scores 1.
+pub fn compute_fibonacci_levels(
+    swings: &[SwingPoint],
+    settings: &FibonacciSettings,
+    current_price: f64,
+) -> Vec<Level> {
+    let legs = largest_legs(swings, settings.legs);
+    if legs.is_empty() {
+        return Vec::new();
+    }
+
+    let mut projections: Vec<Projection> = Vec::new();
+    for (rank, leg) in legs.iter().enumerate() {
+        let (start, end) = (leg.start.price, leg.end.price);
+        let span = end - start;
+        for &ratio in &RETRACEMENTS {
+            projections.push(Projection {
+                price: end - ratio * span,
+                ratio,
+                leg: rank + 1,
+            });
+        }
+        for &ratio in &EXTENSIONS {
+            projections.push(Projection {
+                price: start + ratio * span,
+                ratio,
+                leg: rank + 1,
+            });
+        }
+    }
+    projections.sort_by(|a, b| {
+        a.price
+            .partial_cmp(&b.price)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+
+    let mut clusters: Vec<Vec<Projection>> = Vec::new();
+    for projection in projections {
+        match clusters.last_mut() {
+            Some(cluster)
+                if projection.price - cluster[cluster.len() - 1].price
+                    <= settings.confluence_tolerance =>
+            {
+                cluster.push(projection)
+            }
+            _ => clusters.push(vec![projection]),
+        }
+    }
+
+    clusters
+        .into_iter()
+        .filter_map(|cluster| {
+            let mut contributing: Vec<usize> = cluster.iter().map(|p| p.leg).collect();
+            contributing.sort_unstable();
+            contributing.dedup();
+            if contributing.len() < settings.min_confluence.max(1) {
+                return None;
+            }
+            let price = cluster.iter().map(|p| p.price).sum::<f64>() / cluster.len() as f64;
+            let low = cluster[0].price - settings.band;
+            let high = cluster[cluster.len() - 1].price + settings.band;
+            let label = cluster
+                .iter()
+                .map(|p| format!("{:.1}% L{}", p.ratio * 100.0, p.leg))
+                .collect::<Vec<_>>()
+                .join(", ");
+            Some(Level::new(
+                price,
+                (low, high),
+                contributing.len() as f64 / legs.len() as f64,
+                LevelSource::Fibonacci,
+                label,
+                current_price,
+            ))
+        })
+        .collect()
+}
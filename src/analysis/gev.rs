@@ -0,0 +1,218 @@
+use statrs::distribution::{ContinuousCDF, Normal};
+
+use crate::analysis::optimize::{invert_positive, nelder_mead, numeric_hessian};
+
+/// Shapes closer to zero than this use the Gumbel limit of the GEV.
+const SHAPE_EPSILON: f64 = 1e-6;
+/// Euler-Mascheroni constant, the mean of the standard Gumbel.
+const EULER_GAMMA: f64 = 0.577_215_664_901_532_9;
+
+/// Generalized extreme value distribution fitted to block maxima by maximum
+/// likelihood.
+#[derive(Debug, Clone)]
+pub struct GevFit {
+    pub location: f64,
+    pub scale: f64,
+    pub shape: f64,
+    /// Asymptotic covariance of `(location, scale, shape)`; `None` for shape
+    /// at or below -0.5, where the MLE is not regular, or a singular
+    /// information matrix.
+    pub covariance: Option<[[f64; 3]; 3]>,
+    pub blocks: usize,
+}
+
+impl GevFit {
+    pub fn location_se(&self) -> Option<f64> {
+        self.covariance.map(|cov| cov[0][0].sqrt())
+    }
+
+    pub fn scale_se(&self) -> Option<f64> {
+        self.covariance.map(|cov| cov[1][1].sqrt())
+    }
+
+    pub fn shape_se(&self) -> Option<f64> {
+        self.covariance.map(|cov| cov[2][2].sqrt())
+    }
+
+    /// Level a block maximum exceeds with probability `prob`, with a
+    /// delta-method interval at `ci_level` when the covariance is available.
+    pub fn return_level(&self, prob: f64, ci_level: f64) -> Option<(f64, Option<(f64, f64)>)> {
+        if !(prob > 0.0 && prob < 1.0) {
+            return None;
+        }
+        let log_y = (-(1.0 - prob).ln()).ln();
+        let (level, gradient) = if self.shape.abs() < SHAPE_EPSILON {
+            (
+                self.location - self.scale * log_y,
+                [1.0, -log_y, 0.5 * self.scale * log_y * log_y],
+            )
+        } else {
+            let power = (-self.shape * log_y).exp();
+            let factor = (1.0 - power) / self.shape;
+            (
+                self.location - self.scale * factor,
+                [
+                    1.0,
+                    -factor,
+                    self.scale / self.shape * (factor - power * log_y),
+                ],
+            )
+        };
+        if !level.is_finite() {
+            return None;
+        }
+        let bounds = self.covariance.and_then(|cov| {
+            let variance: f64 = (0..3)
+                .flat_map(|i| (0..3).map(move |j| (i, j)))
+                .map(|(i, j)| gradient[i] * cov[i][j] * gradient[j])
+                .sum();
+            let z = Normal::new(0.0, 1.0)
+                .ok()?
+                .inverse_cdf(0.5 + 0.5 * ci_level.clamp(0.0, 0.999_999));
+            let se = variance.max(0.0).sqrt();
+            se.is_finite().then_some((level - z * se, level + z * se))
+        });
+        Some((level, bounds))
+    }
+}
+
+/// Fit a GEV to block maxima by maximum likelihood, starting from the
+/// Gumbel method-of-moments estimates.
+pub fn fit_gev(maxima: &[f64]) -> Option<GevFit> {
+    if maxima.len() < 5 || maxima.iter().any(|value| !value.is_finite()) {
+        return None;
+    }
+    let n = maxima.len() as f64;
+    let mean = maxima.iter().sum::<f64>() / n;
+    let variance = maxima
+        .iter()
+        .map(|value| (value - mean).powi(2))
+        .sum::<f64>()
+        / (n - 1.0);
+    let start_scale = (6.0 * variance).sqrt() / std::f64::consts::PI;
+    if !(start_scale.is_finite() && start_scale > 0.0) {
+        return None;
+    }
+    let start_location = mean - EULER_GAMMA * start_scale;
+
+    let objective =
+        |theta: &[f64]| gev_neg_log_likelihood(maxima, theta[0], theta[1].exp(), theta[2]);
+    let (theta, value) = nelder_mead(
+        objective,
+        &[start_location, start_scale.ln(), 0.1],
+        &[0.5 * start_scale, 0.2, 0.1],
+        4000,
+    );
+    if !value.is_finite() {
+        return None;
+    }
+    let (location, scale, shape) = (theta[0], theta[1].exp(), theta[2]);
+
+    let covariance = (shape > -0.5)
+        .then(|| {
+            let hessian = numeric_hessian(
+                |theta: &[f64]| gev_neg_log_likelihood(maxima, theta[0], theta[1], theta[2]),
+                &[location, scale, shape],
+                &[1e-4 * scale, 1e-4 * scale, 1e-4],
+            );
+            let inverse = invert_positive(&hessian)?;
+            Some([
+                [inverse[0][0], inverse[0][1], inverse[0][2]],
+                [inverse[1][0], inverse[1][1], inverse[1][2]],
+                [inverse[2][0], inverse[2][1], inverse[2][2]],
+            ])
+        })
+        .flatten();
+    Some(GevFit {
+        location,
+        scale,
+        shape,
+        covariance,
+        blocks: maxima.len(),
+    })
+}
+
+/// Negative GEV log-likelihood; infinite outside the support or for shape
+/// at or below -1, where the likelihood is unbounded.
+pub fn gev_neg_log_likelihood(maxima: &[f64], location: f64, scale: f64, shape: f64) -> f64 {
+    if !scale.is_finite() || scale <= 0.0 || !shape.is_finite() || shape <= -1.0 {
+        return f64::INFINITY;
+    }
+    let n = maxima.len() as f64;
+    if shape.abs() < SHAPE_EPSILON {
+        let sum: f64 = maxima
+            .iter()
+            .map(|&value| {
+                let z = (value - location) / scale;
+                z + (-z).exp()
+            })
+            .sum();
+        return n * scale.ln() + sum;
+    }
+    let mut sum = 0.0;
+    for &value in maxima {
+        let t = 1.0 + shape * (value - location) / scale;
+        if t <= 0.0 {
+            return f64::INFINITY;
+        }
+        sum += (1.0 + 1.0 / shape) * t.ln() + t.powf(-1.0 / shape);
+    }
+    n * scale.ln() + sum
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gev(location: f64, scale: f64, shape: f64, covariance: Option<[[f64; 3]; 3]>) -> GevFit {
+        GevFit {
+            location,
+            scale,
+            shape,
+            covariance,
+            blocks: 0,
+        }
+    }
+
+    #[test]
+    fn return_levels_match_textbook_quantiles() {
+        // A block maximum exceeds z_p with probability p = 0.01 when
+        // z_p = mu - sigma / xi * (1 - y^-xi), y = -ln(1 - p).
+        let y = -(0.99_f64).ln();
+        let (gumbel, _) = gev(10.0, 2.0, 0.0, None).return_level(0.01, 0.9).unwrap();
+        assert!((gumbel - (10.0 - 2.0 * y.ln())).abs() < 1e-9);
+        let (frechet, _) = gev(10.0, 2.0, 0.2, None).return_level(0.01, 0.9).unwrap();
+        assert!((frechet - (10.0 + 2.0 / 0.2 * (y.powf(-0.2) - 1.0))).abs() < 1e-9);
+    }
+
+    #[test]
+    fn location_variance_shifts_the_interval_evenly() {
+        let covariance = [[0.25, 0.0, 0.0], [0.0, 0.0, 0.0], [0.0, 0.0, 0.0]];
+        let (level, bounds) = gev(10.0, 2.0, 0.2, Some(covariance))
+            .return_level(0.01, 0.9)
+            .unwrap();
+        let (low, high) = bounds.unwrap();
+        let half_width = 1.644_853_626_951_472_2 * 0.5;
+        assert!((level - low - half_width).abs() < 1e-6);
+        assert!((high - level - half_width).abs() < 1e-6);
+    }
+
+    #[test]
+    fn fit_recovers_known_parameters() {
+        let n = 2000;
+        let maxima: Vec<f64> = (0..n)
+            .map(|i| {
+                let p = (i as f64 + 0.5) / n as f64;
+                10.0 + 2.0 / 0.1 * ((-p.ln()).powf(-0.1) - 1.0)
+            })
+            .collect();
+        let fit = fit_gev(&maxima).unwrap();
+        assert!(
+            (fit.location - 10.0).abs() < 0.05,
+            "location {}",
+            fit.location
+        );
+        assert!((fit.scale - 2.0).abs() < 0.05, "scale {}", fit.scale);
+        assert!((fit.shape - 0.1).abs() < 0.02, "shape {}", fit.shape);
+    }
+}
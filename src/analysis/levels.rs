@@ -1,5 +1,5 @@
 use crate::analysis::peaks::DensityPeak;
-use crate::data::{Level, LevelType, PerformanceStats};
+use crate::data::{Level, LevelSource, LevelType, PerformanceStats};
 
 pub fn build_levels(
     peaks: &[DensityPeak],
@@ -29,11 +29,16 @@ pub fn build_levels(
             };
             let prominence_score = (peak.prominence / max_prominence).clamp(0.0, 1.0);
             let confidence = 0.6 * density_score + 0.4 * prominence_score;
-            let base_band = mean_atr * confidence_band_multiplier;
-            let confidence_band = if base_band > 0.0 {
-                base_band
+            let (zone_low, zone_high) = if peak.zone_high > peak.zone_low {
+                (peak.zone_low, peak.zone_high)
             } else {
-                (peak.price.abs() * 0.001).max(0.25)
+                let base_band = mean_atr * confidence_band_multiplier;
+                let band = if base_band > 0.0 {
+                    base_band
+                } else {
+                    (peak.price.abs() * 0.001).max(0.25)
+                };
+                (peak.price - band, peak.price + band)
             };
             let level_type = if peak.price >= current_price {
                 LevelType::Resistance
@@ -43,11 +48,16 @@ pub fn build_levels(
             Level {
                 price: peak.price,
                 density: peak.density,
+                peak_width: peak.width,
                 confidence,
-                confidence_band,
+                zone_low,
+                zone_high,
                 level_type,
+                source: LevelSource::Density,
+                label: String::new(),
                 performance: PerformanceStats::empty(),
                 distance_from_last: (peak.price - current_price).abs(),
+                significance: None,
             }
         })
         .collect();
@@ -0,0 +1,299 @@
+use chrono::{NaiveDate, NaiveTime};
+
+use crate::analysis::sessions::split_sessions;
+use crate::analysis::stats::{evaluate_levels, EvaluationSettings};
+use crate::analysis::volume_profile::{expand_value_area, point_of_control};
+use crate::data::{Bar, Level, LevelSource};
+
+/// Minimum run of single-TPO rows at a session extreme that counts as excess.
+const MIN_TAIL_ROWS: usize = 2;
+
+#[derive(Debug, Clone, Copy)]
+pub struct MarketProfileSettings {
+    /// Price height of one TPO row.
+    pub row_size: f64,
+    /// Length of one letter period in minutes (30 by convention).
+    pub period_minutes: i64,
+    /// Time of day at which period `A` starts.
+    pub session_start: NaiveTime,
+    /// Share of TPOs enclosed by the value area.
+    pub value_area: f64,
+    /// Number of most recent sessions to profile.
+    pub sessions: usize,
+}
+
+/// Time-price-opportunity profile of one session.
+#[derive(Debug, Clone)]
+pub struct TpoProfile {
+    pub date: NaiveDate,
+    pub row_size: f64,
+    /// Price of the lowest row; row `i` covers `origin + i * row_size`.
+    pub origin: f64,
+    /// Letters printed in each row, lowest row first.
+    pub rows: Vec<String>,
+    pub poc: f64,
+    pub value_area_low: f64,
+    pub value_area_high: f64,
+    pub ib_low: f64,
+    pub ib_high: f64,
+    /// Price ranges of single-print runs inside the profile.
+    pub single_prints: Vec<(f64, f64)>,
+    /// Excess at the high: range of the single-print tail, if any.
+    pub high_tail: Option<(f64, f64)>,
+    /// Excess at the low: range of the single-print tail, if any.
+    pub low_tail: Option<(f64, f64)>,
+    /// Extreme row printed by more than one period (no excess).
+    pub poor_high: bool,
+    pub poor_low: bool,
+}
+
+impl TpoProfile {
+    pub fn price_at(&self, idx: usize) -> f64 {
+        self.origin + self.row_size * idx as f64
+    }
+
+    pub fn high(&self) -> f64 {
+        self.price_at(self.rows.len().saturating_sub(1))
+    }
+
+    pub fn low(&self) -> f64 {
+        self.origin
+    }
+
+    fn row_of(&self, price: f64) -> usize {
+        ((price - self.origin) / self.row_size + 1e-9)
+            .floor()
+            .max(0.0) as usize
+    }
+
+    fn relative_count(&self, price: f64) -> f64 {
+        let max_count = self.rows.iter().map(String::len).max().unwrap_or(0);
+        let count = self.rows.get(self.row_of(price)).map_or(0, String::len);
+        count as f64 / max_count.max(1) as f64
+    }
+}
+
+/// Letter for a period index: `A`-`Z`, then `a`-`z`.
+pub fn period_letter(period: usize) -> char {
+    match period {
+        0..=25 => (b'A' + period as u8) as char,
+        26..=51 => (b'a' + (period - 26) as u8) as char,
+        _ => '*',
+    }
+}
+
+/// Build the TPO profile of a single session's bars.
+///
+/// Each bar is assigned to the letter period containing its timestamp and
+/// every row between a period's low and high receives that period's letter
+/// once. The initial balance is the range of the first two periods. Bars with
+/// non-finite prices or a high below their low are skipped.
+pub fn build_tpo_profile(
+    bars: &[Bar],
+    date: NaiveDate,
+    settings: &MarketProfileSettings,
+) -> Option<TpoProfile> {
+    let row_size = settings.row_size;
+    if bars.is_empty() || !row_size.is_finite() || row_size <= 0.0 {
+        return None;
+    }
+    let period_minutes = settings.period_minutes.max(1);
+    let to_row = |price: f64| (price / row_size + 1e-9).floor() as i64;
+
+    // (low, high) per letter period, in period order.
+    let mut periods: Vec<(usize, f64, f64)> = Vec::new();
+    let valid = |bar: &&Bar| bar.low.is_finite() && bar.high.is_finite() && bar.low <= bar.high;
+    for bar in bars.iter().filter(valid) {
+        let minutes = (bar.timestamp.time() - settings.session_start).num_minutes();
+        let period = (minutes.max(0) / period_minutes) as usize;
+        match periods.last_mut() {
+            Some((current, low, high)) if *current == period => {
+                *low = low.min(bar.low);
+                *high = high.max(bar.high);
+            }
+            _ => periods.push((period, bar.low, bar.high)),
+        }
+    }
+
+    let low_row = periods.iter().map(|&(_, low, _)| to_row(low)).min()?;
+    let high_row = periods.iter().map(|&(_, _, high)| to_row(high)).max()?;
+    let mut rows = vec![String::new(); (high_row - low_row + 1) as usize];
+    for &(period, low, high) in &periods {
+        let start = (to_row(low) - low_row) as usize;
+        let end = (to_row(high) - low_row) as usize;
+        for row in &mut rows[start..=end] {
+            row.push(period_letter(period));
+        }
+    }
+
+    let origin = low_row as f64 * row_size;
+    let price_at = |idx: usize| origin + row_size * idx as f64;
+    let counts: Vec<f64> = rows.iter().map(|row| row.len() as f64).collect();
+    let poc_idx = point_of_control(&counts);
+    let (va_lo, va_hi) = expand_value_area(&counts, poc_idx, settings.value_area);
+
+    // Initial balance from the first two periods that actually traded.
+    let first_period = periods[0].0;
+    let (ib_low, ib_high) = periods
+        .iter()
+        .filter(|&&(period, _, _)| period < first_period + 2)
+        .fold(
+            (f64::INFINITY, f64::NEG_INFINITY),
+            |(lo, hi), &(_, low, high)| (lo.min(low), hi.max(high)),
+        );
+
+    let top = rows.len() - 1;
+    let single = |idx: usize| rows[idx].len() == 1;
+    let low_run = (0..rows.len()).take_while(|&idx| single(idx)).count();
+    let high_run = (0..rows.len()).rev().take_while(|&idx| single(idx)).count();
+    let low_tail = (low_run >= MIN_TAIL_ROWS && low_run < rows.len())
+        .then(|| (price_at(0), price_at(low_run - 1) + row_size));
+    let high_tail = (high_run >= MIN_TAIL_ROWS && high_run < rows.len())
+        .then(|| (price_at(top + 1 - high_run), price_at(top) + row_size));
+
+    let mut single_prints = Vec::new();
+    let mut idx = low_run;
+    while idx < rows.len().saturating_sub(high_run) {
+        if single(idx) {
+            let start = idx;
+            while idx + 1 < rows.len() - high_run && single(idx + 1) {
+                idx += 1;
+            }
+            single_prints.push((price_at(start), price_at(idx) + row_size));
+        }
+        idx += 1;
+    }
+
+    Some(TpoProfile {
+        date,
+        row_size,
+        origin,
+        poc: price_at(poc_idx),
+        value_area_low: price_at(va_lo),
+        value_area_high: price_at(va_hi),
+        ib_low,
+        ib_high,
+        single_prints,
+        high_tail,
+        low_tail,
+        poor_high: rows.len() > 1 && rows[top].len() > 1,
+        poor_low: rows.len() > 1 && rows[0].len() > 1,
+        rows,
+    })
+}
+
+/// TPO profiles of the most recent sessions, oldest first.
+pub fn build_tpo_profiles(bars: &[Bar], settings: &MarketProfileSettings) -> Vec<TpoProfile> {
+    let sessions = split_sessions(bars);
+    let skip = sessions.len().saturating_sub(settings.sessions);
+    sessions
+        .iter()
+        .skip(skip)
+        .filter_map(|session| build_tpo_profile(session.bars(bars), session.date, settings))
+        .collect()
+}
+
+/// Market-profile levels: TPO POC, value-area edges and IB high/low with a
+/// symmetric band, plus single prints, poor extremes and excess tails whose
+/// zones span the rows they cover.
+///
+/// Structural references (singles, poor extremes, tails) are scored by how
+/// thin the profile is there; POC, value area and IB by relative TPO count.
+///
+/// Each profile's levels are evaluated only on the `bars` after its session,
+/// with the session's last bar as context. `atr` must be aligned with `bars`.
+pub fn market_profile_levels(
+    profiles: &[TpoProfile],
+    bars: &[Bar],
+    atr: &[f64],
+    band: f64,
+    evaluation: &EvaluationSettings,
+    current_price: f64,
+) -> Vec<Level> {
+    let mut scored = Vec::new();
+    for profile in profiles {
+        let mut levels = Vec::new();
+        let date = profile.date.format("%Y-%m-%d");
+        let band = band.max(profile.row_size);
+        let mut push = |name: &str, price: f64, zone: (f64, f64), confidence: f64| {
+            levels.push(Level::new(
+                price,
+                zone,
+                confidence,
+                LevelSource::MarketProfile,
+                format!("{name} {date}"),
+                current_price,
+            ));
+        };
+
+        for (name, price) in [
+            ("POC", profile.poc),
+            ("VAH", profile.value_area_high),
+            ("VAL", profile.value_area_low),
+            ("IBH", profile.ib_high),
+            ("IBL", profile.ib_low),
+        ] {
+            push(
+                name,
+                price,
+                (price - band, price + band),
+                profile.relative_count(price),
+            );
+        }
+
+        for &(low, high) in &profile.single_prints {
+            let mid = 0.5 * (low + high);
+            push(
+                "single",
+                mid,
+                (low, high),
+                1.0 - profile.relative_count(mid),
+            );
+        }
+        if profile.poor_high {
+            let high = profile.high();
+            push(
+                "poor high",
+                high,
+                (high, high + profile.row_size),
+                profile.relative_count(high),
+            );
+        }
+        if profile.poor_low {
+            let low = profile.low();
+            push(
+                "poor low",
+                low,
+                (low, low + profile.row_size),
+                profile.relative_count(low),
+            );
+        }
+        // The inner edge of a tail is where the auction rejected price.
+        if let Some((low, high)) = profile.high_tail {
+            push(
+                "excess high",
+                low,
+                (low, high),
+                1.0 - profile.relative_count(low),
+            );
+        }
+        if let Some((low, high)) = profile.low_tail {
+            push(
+                "excess low",
+                high,
+                (low, high),
+                1.0 - profile.relative_count(low),
+            );
+        }
+
+        let after = bars.partition_point(|bar| bar.timestamp.date_naive() <= profile.date);
+        let context = after.saturating_sub(1);
+        scored.extend(evaluate_levels(
+            levels,
+            bars.get(context..).unwrap_or(&[]),
+            atr.get(context..).unwrap_or(&[]),
+            evaluation,
+        ));
+    }
+    scored
+}
@@ -2,18 +2,49 @@ pub mod atr;
 pub mod clustering;
 pub mod density;
 pub mod evt;
+pub mod fft;
+pub mod fibonacci;
+pub mod gev;
+pub mod gpd;
 pub mod levels;
+pub mod market_profile;
+pub mod optimize;
 
 pub mod peaks;
+pub mod pivots;
+pub mod projections;
+pub mod reference;
+pub mod round_numbers;
+pub mod sessions;
+pub mod significance;
 pub mod stats;
 pub mod swings;
+pub mod volume_profile;
+pub mod vwap;
+pub mod walk_forward;
 
-pub use atr::compute_atr;
+pub use atr::{compute_atr, compute_causal_atr};
 pub use clustering::{auto_dbscan_epsilon, cluster_swings, ClusterResult};
-pub use density::{compute_density_curve, DensityAnalysis};
-pub use evt::compute_evt_resistances;
+pub use density::{compute_density_curve, DensityAnalysis, DensitySettings, KdeEngine};
+pub use evt::{
+    compute_evt_resistances, compute_evt_supports, compute_gev_levels, EvtFallback, EvtSettings,
+    GevSettings, TailSide,
+};
+pub use fibonacci::{compute_fibonacci_levels, FibonacciSettings};
 pub use levels::build_levels;
+pub use market_profile::{build_tpo_profiles, market_profile_levels, MarketProfileSettings};
 
-pub use peaks::detect_peaks;
-pub use stats::evaluate_levels;
+pub use peaks::{detect_peaks, PeakSettings};
+pub use pivots::{compute_pivot_levels, PivotSettings};
+pub use projections::{
+    atr_projection, expected_move_levels, session_true_range, session_volatility,
+    vol_cone_projection, ExpectedMoveSettings,
+};
+pub use reference::{compute_reference_levels, merge_reference_levels, ReferenceSettings};
+pub use round_numbers::{auto_increments, compute_round_number_levels, RoundNumberSettings};
+pub use significance::{test_significance, SignificanceSettings};
+pub use stats::{evaluate_levels, EvaluationSettings, TradeSettings};
 pub use swings::detect_swings;
+pub use volume_profile::{compute_volume_profile_levels, VolumeProfileSettings};
+pub use vwap::{anchored_vwap_levels, builtin_anchors, timestamp_anchor, VwapSettings};
+pub use walk_forward::{walk_forward, WalkForwardSettings};
@@ -0,0 +1,207 @@
+/// Minimise `f` with the Nelder-Mead simplex method.
+///
+/// `step` gives the initial simplex edge along each coordinate. Non-finite
+/// objective values are treated as `+inf`, so constraints can be expressed by
+/// returning infinity outside the feasible region. Returns the best point and
+/// its objective value.
+pub fn nelder_mead<F>(f: F, start: &[f64], step: &[f64], max_iter: usize) -> (Vec<f64>, f64)
+where
+    F: Fn(&[f64]) -> f64,
+{
+    const TOLERANCE: f64 = 1e-10;
+    let eval = |x: &[f64]| {
+        let value = f(x);
+        if value.is_finite() {
+            value
+        } else {
+            f64::INFINITY
+        }
+    };
+
+    let dim = start.len();
+    let mut simplex: Vec<(Vec<f64>, f64)> = Vec::with_capacity(dim + 1);
+    simplex.push((start.to_vec(), eval(start)));
+    for axis in 0..dim {
+        let mut vertex = start.to_vec();
+        vertex[axis] += step.get(axis).copied().unwrap_or(1.0);
+        let value = eval(&vertex);
+        simplex.push((vertex, value));
+    }
+
+    for _ in 0..max_iter {
+        simplex.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
+        let best = simplex[0].1;
+        let worst = simplex[dim].1;
+        if (worst - best).abs() <= TOLERANCE * (best.abs() + TOLERANCE) {
+            break;
+        }
+
+        let centroid: Vec<f64> = (0..dim)
+            .map(|axis| simplex[..dim].iter().map(|(x, _)| x[axis]).sum::<f64>() / dim as f64)
+            .collect();
+        let towards = |scale: f64| -> Vec<f64> {
+            centroid
+                .iter()
+                .zip(&simplex[dim].0)
+                .map(|(c, w)| c + scale * (w - c))
+                .collect()
+        };
+
+        let reflected = towards(-1.0);
+        let reflected_value = eval(&reflected);
+        if reflected_value < simplex[0].1 {
+            let expanded = towards(-2.0);
+            let expanded_value = eval(&expanded);
+            simplex[dim] = if expanded_value < reflected_value {
+                (expanded, expanded_value)
+            } else {
+                (reflected, reflected_value)
+            };
+        } else if reflected_value < simplex[dim - 1].1 {
+            simplex[dim] = (reflected, reflected_value);
+        } else {
+            let contracted = if reflected_value < worst {
+                towards(-0.5)
+            } else {
+                towards(0.5)
+            };
+            let contracted_value = eval(&contracted);
+            if contracted_value < worst.min(reflected_value) {
+                simplex[dim] = (contracted, contracted_value);
+            } else {
+                // Shrink every vertex towards the best one.
+                let anchor = simplex[0].0.clone();
+                for (vertex, value) in simplex.iter_mut().skip(1) {
+                    for (x, a) in vertex.iter_mut().zip(&anchor) {
+                        *x = a + 0.5 * (*x - a);
+                    }
+                    *value = eval(vertex);
+                }
+            }
+        }
+    }
+
+    simplex.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
+    simplex.swap_remove(0)
+}
+
+/// Maximise a unimodal `f` on `[lo, hi]` by golden-section search. Returns the
+/// maximiser and the maximum.
+pub fn golden_section_max<F>(f: F, lo: f64, hi: f64, iterations: usize) -> (f64, f64)
+where
+    F: Fn(f64) -> f64,
+{
+    let ratio = (5.0_f64.sqrt() - 1.0) / 2.0;
+    let (mut a, mut b) = (lo, hi);
+    let mut c = b - ratio * (b - a);
+    let mut d = a + ratio * (b - a);
+    let mut f_c = f(c);
+    let mut f_d = f(d);
+    for _ in 0..iterations {
+        if f_c > f_d {
+            b = d;
+            d = c;
+            f_d = f_c;
+            c = b - ratio * (b - a);
+            f_c = f(c);
+        } else {
+            a = c;
+            c = d;
+            f_c = f_d;
+            d = a + ratio * (b - a);
+            f_d = f(d);
+        }
+    }
+    let x = 0.5 * (a + b);
+    (x, f(x))
+}
+
+/// Inverse of a symmetric 2x2 matrix, if it is positive definite.
+pub fn invert_positive_2x2(m: [[f64; 2]; 2]) -> Option<[[f64; 2]; 2]> {
+    let det = m[0][0] * m[1][1] - m[0][1] * m[1][0];
+    if !(det.is_finite() && det > 0.0 && m[0][0] > 0.0) {
+        return None;
+    }
+    Some([
+        [m[1][1] / det, -m[0][1] / det],
+        [-m[1][0] / det, m[0][0] / det],
+    ])
+}
+
+/// Central-difference Hessian of `f` at `x` with per-coordinate steps `h`.
+pub fn numeric_hessian<F>(f: F, x: &[f64], h: &[f64]) -> Vec<Vec<f64>>
+where
+    F: Fn(&[f64]) -> f64,
+{
+    let dim = x.len();
+    let at = |offsets: &[(usize, f64)]| {
+        let mut point = x.to_vec();
+        for &(axis, delta) in offsets {
+            point[axis] += delta;
+        }
+        f(&point)
+    };
+    let centre = f(x);
+    let mut hessian = vec![vec![0.0; dim]; dim];
+    for i in 0..dim {
+        hessian[i][i] = (at(&[(i, h[i])]) - 2.0 * centre + at(&[(i, -h[i])])) / (h[i] * h[i]);
+        for j in (i + 1)..dim {
+            let value = (at(&[(i, h[i]), (j, h[j])])
+                - at(&[(i, h[i]), (j, -h[j])])
+                - at(&[(i, -h[i]), (j, h[j])])
+                + at(&[(i, -h[i]), (j, -h[j])]))
+                / (4.0 * h[i] * h[j]);
+            hessian[i][j] = value;
+            hessian[j][i] = value;
+        }
+    }
+    hessian
+}
+
+/// Inverse of a symmetric matrix via its Cholesky factor, if it is positive
+/// definite.
+pub fn invert_positive(m: &[Vec<f64>]) -> Option<Vec<Vec<f64>>> {
+    let dim = m.len();
+    let mut lower = vec![vec![0.0; dim]; dim];
+    for i in 0..dim {
+        for j in 0..=i {
+            let sum: f64 = (0..j).map(|k| lower[i][k] * lower[j][k]).sum();
+            if i == j {
+                let pivot = m[i][i] - sum;
+                if !(pivot.is_finite() && pivot > 0.0) {
+                    return None;
+                }
+                lower[i][i] = pivot.sqrt();
+            } else {
+                lower[i][j] = (m[i][j] - sum) / lower[j][j];
+            }
+        }
+    }
+
+    // Columns of L^-1 by forward substitution, then A^-1 = L^-T L^-1.
+    let columns: Vec<Vec<f64>> = (0..dim)
+        .map(|col| {
+            let mut column = vec![0.0; dim];
+            for row in col..dim {
+                let target = if row == col { 1.0 } else { 0.0 };
+                let sum: f64 = (col..row).map(|k| lower[row][k] * column[k]).sum();
+                column[row] = (target - sum) / lower[row][row];
+            }
+            column
+        })
+        .collect();
+    let inverse: Vec<Vec<f64>> = columns
+        .iter()
+        .map(|a| {
+            columns
+                .iter()
+                .map(|b| a.iter().zip(b).map(|(x, y)| x * y).sum())
+                .collect()
+        })
+        .collect();
+    inverse
+        .iter()
+        .flatten()
+        .all(|value| value.is_finite())
+        .then_some(inverse)
+}
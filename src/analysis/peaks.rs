@@ -4,38 +4,197 @@ use crate::analysis::density::DensityAnalysis;
 pub struct DensityPeak {
     pub price: f64,
     pub density: f64,
+    /// Topographic prominence: height above the highest saddle connecting the
+    /// peak to higher ground (or to the grid edge for the tallest peak).
     pub prominence: f64,
+    /// Width in price units at half prominence.
+    pub width: f64,
+    /// Where the density falls to `zone_fraction` of the peak height on each
+    /// side, stopping early at the bottom of a valley.
+    pub zone_low: f64,
+    pub zone_high: f64,
 }
 
-pub fn detect_peaks(density: &DensityAnalysis) -> Vec<DensityPeak> {
+/// Filters applied after prominence is measured.
+#[derive(Debug, Clone, Copy)]
+pub struct PeakSettings {
+    /// Minimum prominence as a fraction of the maximum density.
+    pub min_prominence: f64,
+    /// Minimum peak height as a fraction of the maximum density.
+    pub min_relative_height: f64,
+    /// Minimum distance between retained peaks in ATR multiples.
+    pub min_separation_atr: f64,
+    pub mean_atr: f64,
+    /// Fraction of the peak height that bounds the level zone.
+    pub zone_fraction: f64,
+}
+
+pub fn detect_peaks(density: &DensityAnalysis, settings: &PeakSettings) -> Vec<DensityPeak> {
     if density.grid.len() < 3 {
         return Vec::new();
     }
 
-    let mut peaks = Vec::new();
-    for i in 1..density.grid.len() - 1 {
-        let prev = &density.grid[i - 1];
-        let curr = &density.grid[i];
-        let next = &density.grid[i + 1];
-
-        if curr.density <= prev.density || curr.density <= next.density {
-            continue;
-        }
-
-        let left_base = prev.density.min(curr.density);
-        let right_base = next.density.min(curr.density);
-        let prominence = curr.density - left_base.min(right_base);
-        peaks.push(DensityPeak {
-            price: curr.price,
-            density: curr.density,
-            prominence,
-        });
+    let values: Vec<f64> = density.grid.iter().map(|point| point.density).collect();
+    let max_density = values.iter().copied().fold(0.0, f64::max);
+    if max_density <= 0.0 {
+        return Vec::new();
     }
 
+    let mut peaks: Vec<DensityPeak> = local_maxima(&values)
+        .into_iter()
+        .map(|index| measure_peak(density, &values, index, settings.zone_fraction))
+        .filter(|peak| {
+            peak.prominence >= settings.min_prominence * max_density
+                && peak.density >= settings.min_relative_height * max_density
+        })
+        .collect();
+
     peaks.sort_by(|a, b| {
         b.prominence
             .partial_cmp(&a.prominence)
             .unwrap_or(std::cmp::Ordering::Equal)
     });
+
+    let separation = settings.min_separation_atr * settings.mean_atr;
+    if separation > 0.0 {
+        let mut kept: Vec<DensityPeak> = Vec::with_capacity(peaks.len());
+        for peak in peaks {
+            if kept
+                .iter()
+                .all(|other| (other.price - peak.price).abs() >= separation)
+            {
+                kept.push(peak);
+            }
+        }
+        peaks = kept;
+    }
     peaks
 }
+
+/// Indices of strict local maxima; flat-topped peaks report the middle of the plateau.
+pub fn local_maxima(values: &[f64]) -> Vec<usize> {
+    let mut maxima = Vec::new();
+    let mut i = 1;
+    while i < values.len() - 1 {
+        if values[i - 1] < values[i] {
+            let mut ahead = i + 1;
+            while ahead < values.len() - 1 && values[ahead] == values[i] {
+                ahead += 1;
+            }
+            if values[ahead] < values[i] {
+                maxima.push((i + ahead - 1) / 2);
+                i = ahead;
+                continue;
+            }
+        }
+        i += 1;
+    }
+    maxima
+}
+
+/// Prominence of the maximum at `index` together with the grid indices of the
+/// lowest points on each side before higher ground (or the edge) is reached.
+pub fn topographic_prominence(values: &[f64], index: usize) -> (f64, usize, usize) {
+    let height = values[index];
+
+    let mut left_min = height;
+    let mut left_base = index;
+    for j in (0..index).rev() {
+        if values[j] > height {
+            break;
+        }
+        if values[j] < left_min {
+            left_min = values[j];
+            left_base = j;
+        }
+    }
+
+    let mut right_min = height;
+    let mut right_base = index;
+    for (j, &value) in values.iter().enumerate().skip(index + 1) {
+        if value > height {
+            break;
+        }
+        if value < right_min {
+            right_min = value;
+            right_base = j;
+        }
+    }
+
+    (height - left_min.max(right_min), left_base, right_base)
+}
+
+fn measure_peak(
+    density: &DensityAnalysis,
+    values: &[f64],
+    index: usize,
+    zone_fraction: f64,
+) -> DensityPeak {
+    let height = values[index];
+    let (prominence, left_base, right_base) = topographic_prominence(values, index);
+    let reference = height - prominence / 2.0;
+
+    let mut left_ip = density.grid[left_base].price;
+    for j in (left_base..index).rev() {
+        if values[j] <= reference {
+            left_ip = interpolate_crossing(density, values, j, j + 1, reference);
+            break;
+        }
+    }
+    let mut right_ip = density.grid[right_base].price;
+    for j in index + 1..=right_base {
+        if values[j] <= reference {
+            right_ip = interpolate_crossing(density, values, j - 1, j, reference);
+            break;
+        }
+    }
+
+    let zone_level = height * zone_fraction.clamp(0.0, 1.0);
+    let mut zone_low = density.grid[0].price;
+    for j in (0..index).rev() {
+        if values[j] <= zone_level {
+            zone_low = interpolate_crossing(density, values, j, j + 1, zone_level);
+            break;
+        }
+        if values[j] > values[j + 1] {
+            zone_low = density.grid[j + 1].price;
+            break;
+        }
+    }
+    let mut zone_high = density.grid[values.len() - 1].price;
+    for j in index + 1..values.len() {
+        if values[j] <= zone_level {
+            zone_high = interpolate_crossing(density, values, j - 1, j, zone_level);
+            break;
+        }
+        if values[j] > values[j - 1] {
+            zone_high = density.grid[j - 1].price;
+            break;
+        }
+    }
+
+    DensityPeak {
+        price: density.grid[index].price,
+        density: height,
+        prominence,
+        width: (right_ip - left_ip).max(0.0),
+        zone_low,
+        zone_high,
+    }
+}
+
+/// Price at which the density between grid points `a` and `b` crosses `level`.
+fn interpolate_crossing(
+    density: &DensityAnalysis,
+    values: &[f64],
+    a: usize,
+    b: usize,
+    level: f64,
+) -> f64 {
+    let (pa, pb) = (density.grid[a].price, density.grid[b].price);
+    let (va, vb) = (values[a], values[b]);
+    if (vb - va).abs() < f64::EPSILON {
+        return 0.5 * (pa + pb);
+    }
+    pa + (level - va) / (vb - va) * (pb - pa)
+}
@@ -0,0 +1,254 @@
+use clap::ValueEnum;
+
+use crate::analysis::sessions::{split_periods, CalendarPeriod};
+use crate::analysis::stats::{evaluate_levels, EvaluationSettings};
+use crate::data::{Bar, Level, LevelSource, PerformanceStats};
+
+const PIVOT_NAMES: [&str; 7] = ["P", "R1", "R2", "R3", "S1", "S2", "S3"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum PivotMethod {
+    /// Floor-trader pivots: P = (H + L + C) / 3.
+    Classic,
+    /// Pivot weighted towards the new period's open: P = (H + L + 2 O) / 4.
+    Woodie,
+    /// Close-centred levels at 1.1/12, 1.1/6 and 1.1/4 of the range.
+    Camarilla,
+    /// Classic pivot with 0.382, 0.618 and 1.0 range retracements.
+    Fibonacci,
+}
+
+impl PivotMethod {
+    pub fn label(&self) -> &'static str {
+        match self {
+            PivotMethod::Classic => "classic",
+            PivotMethod::Woodie => "woodie",
+            PivotMethod::Camarilla => "camarilla",
+            PivotMethod::Fibonacci => "fibonacci",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PivotSettings {
+    /// Half-width of the zone around each pivot price.
+    pub band: f64,
+    pub evaluation: EvaluationSettings,
+}
+
+/// Pooled historical performance of one pivot method on one period.
+#[derive(Debug, Clone)]
+pub struct PivotSummary {
+    pub method: PivotMethod,
+    pub period: CalendarPeriod,
+    /// Number of periods the pivots were scored on, including the current one.
+    pub periods: usize,
+    pub performance: PerformanceStats,
+}
+
+pub struct PivotAnalysis {
+    pub levels: Vec<Level>,
+    pub summaries: Vec<PivotSummary>,
+}
+
+/// P, R1-R3 and S1-S3 (in `PIVOT_NAMES` order) from the prior period's high,
+/// low and close and the new period's open.
+pub fn pivot_points(method: PivotMethod, high: f64, low: f64, close: f64, open: f64) -> [f64; 7] {
+    let range = high - low;
+    match method {
+        PivotMethod::Classic | PivotMethod::Woodie => {
+            let p = if method == PivotMethod::Woodie {
+                (high + low + 2.0 * open) / 4.0
+            } else {
+                (high + low + close) / 3.0
+            };
+            [
+                p,
+                2.0 * p - low,
+                p + range,
+                high + 2.0 * (p - low),
+                2.0 * p - high,
+                p - range,
+                low - 2.0 * (high - p),
+            ]
+        }
+        PivotMethod::Camarilla => {
+            let p = (high + low + close) / 3.0;
+            let step = |divisor: f64| range * 1.1 / divisor;
+            [
+                p,
+                close + step(12.0),
+                close + step(6.0),
+                close + step(4.0),
+                close - step(12.0),
+                close - step(6.0),
+                close - step(4.0),
+            ]
+        }
+        PivotMethod::Fibonacci => {
+            let p = (high + low + close) / 3.0;
+            [
+                p,
+                p + 0.382 * range,
+                p + 0.618 * range,
+                p + range,
+                p - 0.382 * range,
+                p - 0.618 * range,
+                p - range,
+            ]
+        }
+    }
+}
+
+/// Current pivots for each method and period, scored on history.
+///
+/// Every period in `bars` gets the pivots implied by the period before it;
+/// those are evaluated on that period's bars only, typed against its open,
+/// and the statistics are pooled per pivot name. The last bar of the prior
+/// period is passed along as context, so a pivot the period opens on still
+/// arms for its first touch when that bar lay outside the zone. The emitted levels are the
+/// pivots of the latest period and carry the pooled performance, so they need
+/// no further evaluation. `atr` must be aligned with `bars`.
+pub fn compute_pivot_levels(
+    bars: &[Bar],
+    atr: &[f64],
+    methods: &[PivotMethod],
+    periods: &[CalendarPeriod],
+    settings: &PivotSettings,
+    current_price: f64,
+) -> PivotAnalysis {
+    let mut levels = Vec::new();
+    let mut summaries = Vec::new();
+
+    for &period in periods {
+        let spans = split_periods(bars, period);
+        if spans.len() < 2 {
+            continue;
+        }
+        for &method in methods {
+            let mut pooled: Vec<PerformanceStats> = PIVOT_NAMES
+                .iter()
+                .map(|_| PerformanceStats::empty())
+                .collect();
+            let mut current = [0.0; 7];
+            for pair in spans.windows(2) {
+                let prior = pair[0].bars(bars);
+                let span = pair[1].bars(bars);
+                let (high, low) = prior.iter().fold((f64::MIN, f64::MAX), |(hi, lo), bar| {
+                    (hi.max(bar.high), lo.min(bar.low))
+                });
+                let close = prior[prior.len() - 1].close;
+                current = pivot_points(method, high, low, close, span[0].open);
+
+                let historical =
+                    pivot_levels(&current, method, period, settings.band, span[0].open);
+                let scored_range = pair[1].range.start - 1..pair[1].range.end;
+                let scored = evaluate_levels(
+                    historical,
+                    &bars[scored_range.clone()],
+                    atr.get(scored_range).unwrap_or(&[]),
+                    &settings.evaluation,
+                );
+                for (stats, level) in pooled.iter_mut().zip(&scored) {
+                    stats.merge(&level.performance);
+                }
+            }
+
+            let mut summary = PerformanceStats::empty();
+            for stats in &pooled {
+                summary.merge(stats);
+            }
+            summaries.push(PivotSummary {
+                method,
+                period,
+                periods: spans.len() - 1,
+                performance: summary,
+            });
+
+            let mut latest = pivot_levels(&current, method, period, settings.band, current_price);
+            for (level, stats) in latest.iter_mut().zip(pooled) {
+                level.performance = stats;
+            }
+            levels.extend(latest);
+        }
+    }
+    PivotAnalysis { levels, summaries }
+}
+
+fn pivot_levels(
+    prices: &[f64; 7],
+    method: PivotMethod,
+    period: CalendarPeriod,
+    band: f64,
+    reference_price: f64,
+) -> Vec<Level> {
+    PIVOT_NAMES
+        .iter()
+        .zip(prices)
+        .map(|(name, &price)| {
+            // The central pivot carries the most weight; R/S levels decay with rank.
+            let rank = name[1..].parse::<f64>().unwrap_or(0.0);
+            Level::new(
+                price,
+                (price - band, price + band),
+                1.0 / (1.0 + rank),
+                LevelSource::Pivot,
+                format!("{} {} {name}", method.label(), period.label()),
+                reference_price,
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_pivots(method: PivotMethod, expected: [f64; 7]) {
+        let pivots = pivot_points(method, 110.0, 90.0, 100.0, 104.0);
+        for ((name, got), want) in PIVOT_NAMES.iter().zip(pivots).zip(expected) {
+            assert!((got - want).abs() < 1e-9, "{name}: {got} != {want}");
+        }
+    }
+
+    #[test]
+    fn classic_pivots() {
+        assert_pivots(
+            PivotMethod::Classic,
+            [100.0, 110.0, 120.0, 130.0, 90.0, 80.0, 70.0],
+        );
+    }
+
+    #[test]
+    fn woodie_pivots_weight_the_open() {
+        assert_pivots(
+            PivotMethod::Woodie,
+            [102.0, 114.0, 122.0, 134.0, 94.0, 82.0, 74.0],
+        );
+    }
+
+    #[test]
+    fn camarilla_pivots() {
+        let step = 22.0 / 12.0;
+        assert_pivots(
+            PivotMethod::Camarilla,
+            [
+                100.0,
+                100.0 + step,
+                100.0 + 2.0 * step,
+                100.0 + 3.0 * step,
+                100.0 - step,
+                100.0 - 2.0 * step,
+                100.0 - 3.0 * step,
+            ],
+        );
+    }
+
+    #[test]
+    fn fibonacci_pivots() {
+        assert_pivots(
+            PivotMethod::Fibonacci,
+            [100.0, 107.64, 112.36, 120.0, 92.36, 87.64, 80.0],
+        );
+    }
+}
@@ -0,0 +1,281 @@
+use clap::ValueEnum;
+use statrs::distribution::{ContinuousCDF, Normal};
+
+use crate::analysis::evt::TailSide;
+use crate::analysis::sessions::{split_sessions, CalendarPeriod};
+use crate::data::{Bar, Level, LevelSource};
+
+/// Mean range of a Brownian path over unit time, in units of its volatility
+/// (`2 * sqrt(2 / pi)`); converts an average range into a volatility.
+const RANGE_TO_SIGMA: f64 = 1.595_769_121_605_731;
+
+/// Volatility estimate behind the expected-move projections.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum VolatilitySource {
+    /// Session true range converted with the Brownian range factor.
+    Atr,
+    /// Standard deviation of session close-to-close log returns.
+    Close,
+}
+
+impl VolatilitySource {
+    pub fn label(&self) -> &'static str {
+        match self {
+            VolatilitySource::Atr => "session ATR",
+            VolatilitySource::Close => "close-to-close",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ExpectedMoveSettings {
+    pub source: VolatilitySource,
+    /// Completed sessions the volatility is estimated from.
+    pub sessions: usize,
+    /// Adjust quantiles for the skewness and kurtosis of session returns.
+    pub skew: bool,
+    /// Half-width of the zone around each projected price.
+    pub band: f64,
+}
+
+pub struct ExpectedMoves {
+    /// Per-session log-return volatility.
+    pub volatility: f64,
+    /// Skewness and excess kurtosis of session returns, when used.
+    pub moments: Option<(f64, f64)>,
+    pub levels: Vec<Level>,
+}
+
+/// Mean true range of the sessions in `bars`, each session's range extended
+/// to the previous session's close.
+pub fn session_true_range(bars: &[Bar]) -> Option<f64> {
+    let sessions = split_sessions(bars);
+    let ranges: Vec<f64> = sessions
+        .windows(2)
+        .map(|pair| {
+            let prior_close = bars[pair[0].range.end - 1].close;
+            let session = pair[1].bars(bars);
+            let high = session
+                .iter()
+                .map(|bar| bar.high)
+                .fold(prior_close, f64::max);
+            let low = session
+                .iter()
+                .map(|bar| bar.low)
+                .fold(prior_close, f64::min);
+            high - low
+        })
+        .collect();
+    (!ranges.is_empty()).then(|| ranges.iter().sum::<f64>() / ranges.len() as f64)
+}
+
+/// Sample standard deviation of session close-to-close log returns.
+pub fn session_volatility(bars: &[Bar]) -> Option<f64> {
+    sample_std(&session_returns(bars))
+}
+
+/// Close-to-close log returns between consecutive sessions.
+fn session_returns(bars: &[Bar]) -> Vec<f64> {
+    split_sessions(bars)
+        .windows(2)
+        .map(|pair| {
+            let prior = bars[pair[0].range.end - 1].close;
+            let close = bars[pair[1].range.end - 1].close;
+            (close / prior).ln()
+        })
+        .filter(|value| value.is_finite())
+        .collect()
+}
+
+/// Projection `multiple` session true ranges from the current price.
+pub fn atr_projection(
+    current_price: f64,
+    session_range: f64,
+    multiple: f64,
+    side: TailSide,
+    band: f64,
+) -> Level {
+    let price = match side {
+        TailSide::Upper => current_price + multiple * session_range,
+        TailSide::Lower => current_price - multiple * session_range,
+    };
+    Level::new(
+        price,
+        (price - band, price + band),
+        0.0,
+        LevelSource::Projection,
+        format!("{multiple:.1}x session ATR"),
+        current_price,
+    )
+}
+
+/// Expected-move levels for the next session, week and month.
+///
+/// The per-session volatility is scaled by the square root of the trading
+/// sessions in the horizon (1, 5 or 21) and each `k` sigma move is projected
+/// up and down from the current price with zero drift. Each level is
+/// labelled with the probability of closing beyond it at the horizon and,
+/// like the ATR projection, carries no confidence score. With `skew` the
+/// normal quantile `k` is replaced by its Cornish-Fisher expansion using the
+/// sample skewness and excess kurtosis, scaled down to the horizon as for a
+/// sum of independent sessions, and the label also shows that adjusted
+/// quantile. The printed probability is still the normal tail `1 - Φ(k)`:
+/// it is exact under the adjusted distribution only because the level sits
+/// at the Cornish-Fisher quantile of that same probability.
+pub fn expected_move_levels(
+    bars: &[Bar],
+    horizons: &[CalendarPeriod],
+    sigmas: &[f64],
+    settings: &ExpectedMoveSettings,
+    current_price: f64,
+) -> Option<ExpectedMoves> {
+    let sessions = split_sessions(bars);
+    let first = sessions.len().saturating_sub(settings.sessions.max(2) + 1);
+    let recent = &bars[sessions.get(first)?.range.start..];
+    let returns = session_returns(recent);
+    let volatility = match settings.source {
+        VolatilitySource::Close => sample_std(&returns)?,
+        VolatilitySource::Atr => session_true_range(recent)? / (RANGE_TO_SIGMA * current_price),
+    };
+    if !(volatility.is_finite() && volatility > 0.0) {
+        return None;
+    }
+    let moments = if settings.skew {
+        sample_moments(&returns)
+    } else {
+        None
+    };
+    let normal = Normal::new(0.0, 1.0).ok()?;
+
+    let mut levels = Vec::new();
+    for &horizon in horizons {
+        let (name, sessions) = match horizon {
+            CalendarPeriod::Day => ("session", 1.0_f64),
+            CalendarPeriod::Week => ("week", 5.0),
+            CalendarPeriod::Month => ("month", 21.0),
+        };
+        let horizon_volatility = volatility * sessions.sqrt();
+        for &k in sigmas.iter().filter(|k| k.is_finite() && **k > 0.0) {
+            let beyond = 1.0 - normal.cdf(k);
+            for direction in [1.0, -1.0] {
+                let adjusted = match moments {
+                    Some((skewness, kurtosis)) => cornish_fisher(
+                        direction * k,
+                        skewness / sessions.sqrt(),
+                        kurtosis / sessions,
+                    ),
+                    None => direction * k,
+                };
+                let price = current_price * (adjusted * horizon_volatility).exp();
+                if !price.is_finite() {
+                    continue;
+                }
+                levels.push(Level::new(
+                    price,
+                    (price - settings.band, price + settings.band),
+                    0.0,
+                    LevelSource::Projection,
+                    format!(
+                        "next {name} {}{k}σ{} P({})={:.1}%",
+                        if direction > 0.0 { "+" } else { "-" },
+                        moments
+                            .map(|_| format!(" (CF z={adjusted:+.2})"))
+                            .unwrap_or_default(),
+                        if direction > 0.0 { "above" } else { "below" },
+                        beyond * 100.0
+                    ),
+                    current_price,
+                ));
+            }
+        }
+    }
+    Some(ExpectedMoves {
+        volatility,
+        moments,
+        levels,
+    })
+}
+
+/// Lognormal one-session move that the next close stays inside with
+/// probability `prob`, given the session return volatility. Like the ATR
+/// projection it carries no confidence score; `prob` only appears in the
+/// label.
+pub fn vol_cone_projection(
+    current_price: f64,
+    volatility: f64,
+    prob: f64,
+    side: TailSide,
+    band: f64,
+) -> Option<Level> {
+    let z = Normal::new(0.0, 1.0).ok()?.inverse_cdf(prob);
+    let price = match side {
+        TailSide::Upper => current_price * (z * volatility).exp(),
+        TailSide::Lower => current_price * (-z * volatility).exp(),
+    };
+    price.is_finite().then(|| {
+        Level::new(
+            price,
+            (price - band, price + band),
+            0.0,
+            LevelSource::Projection,
+            format!("vol cone (non-EVT) p={prob:.4}"),
+            current_price,
+        )
+    })
+}
+
+fn sample_std(values: &[f64]) -> Option<f64> {
+    if values.len() < 2 {
+        return None;
+    }
+    let n = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / n;
+    let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1.0);
+    Some(variance.sqrt())
+}
+
+/// Sample skewness and excess kurtosis.
+fn sample_moments(values: &[f64]) -> Option<(f64, f64)> {
+    if values.len() < 4 {
+        return None;
+    }
+    let n = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / n;
+    let central = |power: i32| values.iter().map(|v| (v - mean).powi(power)).sum::<f64>() / n;
+    let variance = central(2);
+    if variance <= 0.0 {
+        return None;
+    }
+    Some((
+        central(3) / variance.powf(1.5),
+        central(4) / (variance * variance) - 3.0,
+    ))
+}
+
+/// Cornish-Fisher quantile for the standard normal quantile `z` of a
+/// distribution with the given skewness and excess kurtosis.
+fn cornish_fisher(z: f64, skewness: f64, kurtosis: f64) -> f64 {
+    z + (z * z - 1.0) * skewness / 6.0 + (z.powi(3) - 3.0 * z) * kurtosis / 24.0
+        - (2.0 * z.powi(3) - 5.0 * z) * skewness * skewness / 36.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cornish_fisher_matches_hand_expansion() {
+        // 2 + 3 * 0.5 / 6 + 2 * 1 / 24 - 6 * 0.25 / 36 = 2 + 7 / 24.
+        assert!((cornish_fisher(2.0, 0.5, 1.0) - (2.0 + 7.0 / 24.0)).abs() < 1e-12);
+        assert!((cornish_fisher(-2.0, 0.0, 1.0) - (-2.0 - 1.0 / 12.0)).abs() < 1e-12);
+        assert_eq!(cornish_fisher(1.5, 0.0, 0.0), 1.5);
+    }
+
+    #[test]
+    fn sample_moments_of_a_flat_sample() {
+        // Central moments 1.25 and 2.5625 give kurtosis 1.64 - 3.
+        let (skewness, kurtosis) = sample_moments(&[1.0, 2.0, 3.0, 4.0]).unwrap();
+        assert!(skewness.abs() < 1e-12);
+        assert!((kurtosis + 1.36).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,286 @@
+use chrono::Duration;
+
+use crate::analysis::sessions::{split_periods, CalendarPeriod, Session};
+use crate::analysis::stats::{evaluate_levels, EvaluationSettings};
+use crate::data::{Bar, Level, LevelSource, PerformanceStats, RthWindow};
+
+#[derive(Debug, Clone, Copy)]
+pub struct ReferenceSettings {
+    pub rth: RthWindow,
+    /// Length of the opening range in minutes after the RTH open.
+    pub opening_range_minutes: i64,
+    /// Length of the initial balance in minutes after the RTH open.
+    pub initial_balance_minutes: i64,
+    /// Half-width of the zone around each reference price.
+    pub band: f64,
+    pub evaluation: EvaluationSettings,
+}
+
+/// A reference price of one session, known from its `start`-th bar onward.
+struct Reference {
+    /// Name the statistics are pooled under across sessions.
+    key: String,
+    label: String,
+    price: f64,
+    zone: (f64, f64),
+    confidence: f64,
+    start: usize,
+}
+
+impl Reference {
+    fn level(&self, reference_price: f64) -> Level {
+        Level::new(
+            self.price,
+            self.zone,
+            self.confidence,
+            LevelSource::Reference,
+            self.label.clone(),
+            reference_price,
+        )
+    }
+}
+
+/// Mechanical reference levels from the session calendar: prior day, week and
+/// month OHLC, overnight high/low, initial balance, opening range and the RTH
+/// gap to the prior close.
+///
+/// `rth_bars` define the sessions; `raw_bars` (the unfiltered series) supply
+/// the overnight range between the prior RTH close and each open. Prior-day
+/// references score 1, weekly 0.75 and monthly 0.5; intraday references of the
+/// current session score 1.
+///
+/// Every session gets the references known before it, plus its own opening
+/// range and initial balance once they have closed. Those are evaluated on the
+/// rest of that session only, with the bar before as context so a level price
+/// is already trading at is not counted as a fresh touch, and the statistics
+/// are pooled per reference name. The emitted levels are those of the latest
+/// session and carry the pooled performance, so they need no further
+/// evaluation. `atr` must be aligned with `rth_bars`.
+pub fn compute_reference_levels(
+    rth_bars: &[Bar],
+    raw_bars: &[Bar],
+    atr: &[f64],
+    settings: &ReferenceSettings,
+    current_price: f64,
+) -> Vec<Level> {
+    let days = split_periods(rth_bars, CalendarPeriod::Day);
+    let weeks = split_periods(rth_bars, CalendarPeriod::Week);
+    let months = split_periods(rth_bars, CalendarPeriod::Month);
+    let mut pooled: Vec<(String, PerformanceStats)> = Vec::new();
+    let mut latest = Vec::new();
+
+    for day in 0..days.len() {
+        let references =
+            session_references(rth_bars, raw_bars, &days, &weeks, &months, day, settings);
+        let range = &days[day].range;
+        for reference in &references {
+            let first = range.start + reference.start;
+            if first >= range.end {
+                continue;
+            }
+            let context = first.saturating_sub(1);
+            let scored = evaluate_levels(
+                vec![reference.level(rth_bars[first].open)],
+                &rth_bars[context..range.end],
+                atr.get(context..range.end).unwrap_or(&[]),
+                &settings.evaluation,
+            );
+            let Some(level) = scored.first() else {
+                continue;
+            };
+            match pooled.iter_mut().find(|(key, _)| *key == reference.key) {
+                Some((_, stats)) => stats.merge(&level.performance),
+                None => pooled.push((reference.key.clone(), level.performance.clone())),
+            }
+        }
+        latest = references;
+    }
+
+    latest
+        .iter()
+        .map(|reference| {
+            let mut level = reference.level(current_price);
+            if let Some((_, stats)) = pooled.iter().find(|(key, _)| *key == reference.key) {
+                level.performance = stats.clone();
+            }
+            level
+        })
+        .collect()
+}
+
+/// References of `days[day]`, built from the sessions before it and, for the
+/// opening range and initial balance, from its own opening bars.
+fn session_references(
+    rth_bars: &[Bar],
+    raw_bars: &[Bar],
+    days: &[Session],
+    weeks: &[Session],
+    months: &[Session],
+    day: usize,
+    settings: &ReferenceSettings,
+) -> Vec<Reference> {
+    let mut references = Vec::new();
+    let band = settings.band;
+    let mut push = |key: &str, label: String, price: f64, zone: (f64, f64), confidence, start| {
+        references.push(Reference {
+            key: key.to_string(),
+            label,
+            price,
+            zone,
+            confidence,
+            start,
+        });
+    };
+    let open_idx = days[day].range.start;
+
+    for (periods, prefix, confidence) in
+        [(days, "PD", 1.0), (weeks, "PW", 0.75), (months, "PM", 0.5)]
+    {
+        let current = periods.partition_point(|period| period.range.end <= open_idx);
+        let Some(prior) = current.checked_sub(1).map(|idx| &periods[idx]) else {
+            continue;
+        };
+        let (open, high, low, close) = ohlc(prior.bars(rth_bars));
+        for (suffix, price) in [("H", high), ("L", low), ("O", open), ("C", close)] {
+            let name = format!("{prefix}{suffix}");
+            push(
+                &name,
+                name.clone(),
+                price,
+                (price - band, price + band),
+                confidence,
+                0,
+            );
+        }
+    }
+
+    let today = days[day].bars(rth_bars);
+    let prior_close = day
+        .checked_sub(1)
+        .and_then(|idx| days[idx].bars(rth_bars).last());
+    if let Some(prior_close) = prior_close {
+        let from = raw_bars.partition_point(|bar| bar.timestamp <= prior_close.timestamp);
+        let to = raw_bars.partition_point(|bar| bar.timestamp < today[0].timestamp);
+        let overnight: Vec<&Bar> = raw_bars[from..to.max(from)]
+            .iter()
+            .filter(|bar| !settings.rth.contains(&bar.timestamp))
+            .collect();
+        if !overnight.is_empty() {
+            let high = overnight
+                .iter()
+                .map(|bar| bar.high)
+                .fold(f64::MIN, f64::max);
+            let low = overnight.iter().map(|bar| bar.low).fold(f64::MAX, f64::min);
+            push(
+                "ONH",
+                "ONH".to_string(),
+                high,
+                (high - band, high + band),
+                1.0,
+                0,
+            );
+            push(
+                "ONL",
+                "ONL".to_string(),
+                low,
+                (low - band, low + band),
+                1.0,
+                0,
+            );
+        }
+
+        // The gap zone spans prior close to the open; once traded through,
+        // the gap is filled and the level is scored down.
+        let gap_open = today[0].open;
+        let gap = gap_open - prior_close.close;
+        if gap.abs() > f64::EPSILON {
+            let filled = today
+                .iter()
+                .any(|bar| bar.low <= prior_close.close && bar.high >= prior_close.close);
+            let (status, confidence) = if filled {
+                ("filled", 0.5)
+            } else {
+                ("open", 1.0)
+            };
+            push(
+                "gap fill",
+                format!("gap fill {gap:+.2} {status}"),
+                prior_close.close,
+                (
+                    prior_close.close.min(gap_open),
+                    prior_close.close.max(gap_open),
+                ),
+                confidence,
+                0,
+            );
+        }
+    }
+
+    for (name, minutes) in [
+        ("OR", settings.opening_range_minutes),
+        ("IB", settings.initial_balance_minutes),
+    ] {
+        let end = settings.rth.start + Duration::minutes(minutes.max(1));
+        let window = today.partition_point(|bar| bar.timestamp.time() < end);
+        if window == 0 {
+            continue;
+        }
+        let high = today[..window]
+            .iter()
+            .map(|bar| bar.high)
+            .fold(f64::MIN, f64::max);
+        let low = today[..window]
+            .iter()
+            .map(|bar| bar.low)
+            .fold(f64::MAX, f64::min);
+        for (suffix, price) in [("H", high), ("L", low)] {
+            let key = format!("{name}{suffix}");
+            push(
+                &key,
+                key.clone(),
+                price,
+                (price - band, price + band),
+                1.0,
+                window,
+            );
+        }
+    }
+    references
+}
+
+/// Fold reference levels into KDE levels within `tolerance` of them.
+///
+/// A merged reference leaves the KDE price and zone untouched and is recorded
+/// in the KDE level's label; references with no KDE level nearby are returned.
+pub fn merge_reference_levels(
+    levels: &mut [Level],
+    references: Vec<Level>,
+    tolerance: f64,
+) -> Vec<Level> {
+    let mut unmerged = Vec::new();
+    for reference in references {
+        let nearest = levels
+            .iter_mut()
+            .filter(|level| level.source == LevelSource::Density)
+            .map(|level| ((level.price - reference.price).abs(), level))
+            .filter(|(distance, _)| *distance <= tolerance)
+            .min_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+        match nearest {
+            Some((_, level)) => {
+                level.label = if level.label.is_empty() {
+                    format!("+ {}", reference.label)
+                } else {
+                    format!("{} + {}", level.label, reference.label)
+                };
+            }
+            None => unmerged.push(reference),
+        }
+    }
+    unmerged
+}
+
+fn ohlc(bars: &[Bar]) -> (f64, f64, f64, f64) {
+    let high = bars.iter().map(|bar| bar.high).fold(f64::MIN, f64::max);
+    let low = bars.iter().map(|bar| bar.low).fold(f64::MAX, f64::min);
+    (bars[0].open, high, low, bars[bars.len() - 1].close)
+}
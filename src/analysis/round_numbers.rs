@@ -0,0 +1,247 @@
+use crate::analysis::stats::{evaluate_levels, EvaluationSettings};
+use crate::data::{Bar, Level, LevelSource, PerformanceStats};
+
+#[derive(Debug, Clone, Copy)]
+pub struct RoundNumberSettings {
+    /// Number of round prices nearest the current price to emit.
+    pub max_levels: usize,
+    /// Half-width of the zone around each round price.
+    pub band: f64,
+    /// Number of evenly spaced grid shifts tried as the null for each test.
+    pub null_offsets: usize,
+    /// p-value at or below which a round number is marked significant.
+    pub alpha: f64,
+    pub evaluation: EvaluationSettings,
+}
+
+/// Round-number reaction test for one increment, pooled over its levels.
+#[derive(Debug, Clone)]
+pub struct RoundNumberTest {
+    pub increment: f64,
+    pub levels: usize,
+    pub observed: PerformanceStats,
+    /// Mean pooled hit rate of the shifted grids.
+    pub null_hit_rate: f64,
+    /// Number of shifted grids with at least one test.
+    pub nulls: usize,
+    pub p_value: f64,
+}
+
+pub struct RoundNumberAnalysis {
+    pub levels: Vec<Level>,
+    pub tests: Vec<RoundNumberTest>,
+}
+
+/// Default increments by price magnitude: 100/50/25 for index futures
+/// quoted in the thousands, 1.00/0.50 otherwise.
+pub fn auto_increments(price: f64) -> Vec<f64> {
+    if price.abs() >= 1000.0 {
+        vec![100.0, 50.0, 25.0]
+    } else {
+        vec![1.0, 0.5]
+    }
+}
+
+/// Round-number levels with a measured significance.
+///
+/// Candidates are multiples of the finest increment inside the traded range;
+/// the `max_levels` nearest the current price are kept and each is labelled by
+/// the coarsest increment it is a multiple of. The null for a level is the
+/// same level shifted by evenly spaced fractions of its increment, skipping
+/// shifts whose zone would touch a finer round number; with bands wider than
+/// an eighth of the finest increment that clearance is relaxed to a quarter
+/// of it so some shifts survive. The p-value is the
+/// share of shifted copies with a hit rate at least as high as the round
+/// price itself, which also becomes the level's confidence as `1 - p`. The
+/// same comparison pooled over all levels of an increment gives its test.
+/// Levels and tests without any tested null shift are labelled as such
+/// rather than given a p-value.
+pub fn compute_round_number_levels(
+    bars: &[Bar],
+    atr: &[f64],
+    increments: &[f64],
+    settings: &RoundNumberSettings,
+    current_price: f64,
+) -> RoundNumberAnalysis {
+    let mut increments: Vec<f64> = increments
+        .iter()
+        .copied()
+        .filter(|inc| inc.is_finite() && *inc > 0.0)
+        .collect();
+    increments.sort_by(|a, b| b.partial_cmp(a).unwrap_or(std::cmp::Ordering::Equal));
+    let (Some(&finest), false) = (increments.last(), bars.is_empty()) else {
+        return RoundNumberAnalysis {
+            levels: Vec::new(),
+            tests: Vec::new(),
+        };
+    };
+
+    let low = bars.iter().map(|bar| bar.low).fold(f64::MAX, f64::min);
+    let high = bars.iter().map(|bar| bar.high).fold(f64::MIN, f64::max);
+    let mut prices: Vec<f64> = ((low / finest).floor() as i64..=(high / finest).ceil() as i64)
+        .map(|step| step as f64 * finest)
+        .collect();
+    prices.sort_by(|a, b| {
+        (a - current_price)
+            .abs()
+            .partial_cmp(&(b - current_price).abs())
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    prices.truncate(settings.max_levels);
+    prices.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+
+    let evaluate = |levels: Vec<Level>| evaluate_levels(levels, bars, atr, &settings.evaluation);
+    let is_multiple = |price: f64, inc: f64| {
+        let ratio = price / inc;
+        (ratio - ratio.round()).abs() < 1e-6
+    };
+    let clearance = (2.0 * settings.band).min(0.25 * finest);
+
+    let mut levels = Vec::new();
+    let mut tests = Vec::new();
+    for &increment in &increments {
+        let group: Vec<f64> = prices
+            .iter()
+            .copied()
+            .filter(|&price| {
+                is_multiple(price, increment)
+                    && !increments
+                        .iter()
+                        .any(|&coarser| coarser > increment && is_multiple(price, coarser))
+            })
+            .collect();
+        if group.is_empty() {
+            continue;
+        }
+
+        let observed = evaluate(
+            group
+                .iter()
+                .map(|&price| round_level(price, increment, settings.band, current_price))
+                .collect(),
+        );
+        let offsets: Vec<f64> = (1..=settings.null_offsets)
+            .map(|k| increment * k as f64 / (settings.null_offsets + 1) as f64)
+            .filter(|offset| {
+                let phase = offset.rem_euclid(finest);
+                phase.min(finest - phase) >= clearance
+            })
+            .collect();
+        let shifted: Vec<Vec<Level>> = offsets
+            .iter()
+            .map(|&offset| {
+                evaluate(
+                    observed
+                        .iter()
+                        .map(|level| shift_level(level, offset))
+                        .collect(),
+                )
+            })
+            .collect();
+
+        let mut pooled = PerformanceStats::empty();
+        for level in &observed {
+            pooled.merge(&level.performance);
+        }
+        let pooled_nulls: Vec<f64> = shifted
+            .iter()
+            .filter_map(|grid| {
+                let mut stats = PerformanceStats::empty();
+                for level in grid {
+                    stats.merge(&level.performance);
+                }
+                (stats.tests > 0).then_some(stats.hit_rate)
+            })
+            .collect();
+        tests.push(RoundNumberTest {
+            increment,
+            levels: observed.len(),
+            null_hit_rate: mean(&pooled_nulls),
+            nulls: pooled_nulls.len(),
+            p_value: empirical_p_value(&pooled, &pooled_nulls),
+            observed: pooled,
+        });
+
+        for (idx, mut level) in observed.into_iter().enumerate() {
+            let nulls: Vec<f64> = shifted
+                .iter()
+                .map(|grid| &grid[idx].performance)
+                .filter(|stats| stats.tests > 0)
+                .map(|stats| stats.hit_rate)
+                .collect();
+            if nulls.is_empty() {
+                level.confidence = 0.0;
+                level.label = format!("{} no null grid", format_increment(increment));
+                levels.push(level);
+                continue;
+            }
+            let p_value = empirical_p_value(&level.performance, &nulls);
+            level.confidence = 1.0 - p_value;
+            level.label = format!(
+                "{} p={p_value:.2}{}",
+                format_increment(increment),
+                if p_value <= settings.alpha {
+                    " sig"
+                } else {
+                    ""
+                }
+            );
+            levels.push(level);
+        }
+    }
+    levels.sort_by(|a, b| {
+        a.price
+            .partial_cmp(&b.price)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    RoundNumberAnalysis { levels, tests }
+}
+
+/// One-sided p-value of an observed hit rate against null hit rates; 1 when
+/// the level was never tested.
+fn empirical_p_value(observed: &PerformanceStats, nulls: &[f64]) -> f64 {
+    if observed.tests == 0 {
+        return 1.0;
+    }
+    let at_least = nulls
+        .iter()
+        .filter(|&&rate| rate >= observed.hit_rate)
+        .count();
+    (1 + at_least) as f64 / (1 + nulls.len()) as f64
+}
+
+fn round_level(price: f64, increment: f64, band: f64, current_price: f64) -> Level {
+    Level::new(
+        price,
+        (price - band, price + band),
+        0.0,
+        LevelSource::RoundNumber,
+        format_increment(increment),
+        current_price,
+    )
+}
+
+/// Copy of `level` moved by `offset`, keeping its support/resistance type.
+fn shift_level(level: &Level, offset: f64) -> Level {
+    let mut shifted = level.clone();
+    shifted.price += offset;
+    shifted.zone_low += offset;
+    shifted.zone_high += offset;
+    shifted
+}
+
+pub fn format_increment(increment: f64) -> String {
+    if increment.fract().abs() < 1e-9 {
+        format!("{increment:.0}")
+    } else {
+        format!("{increment:.2}")
+    }
+}
+
+fn mean(values: &[f64]) -> f64 {
+    if values.is_empty() {
+        0.0
+    } else {
+        values.iter().sum::<f64>() / values.len() as f64
+    }
+}
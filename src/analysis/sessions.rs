@@ -0,0 +1,71 @@
+use std::ops::Range;
+
+use chrono::{Datelike, NaiveDate};
+use clap::ValueEnum;
+
+use crate::data::Bar;
+
+/// Calendar span used to group sessions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum CalendarPeriod {
+    Day,
+    Week,
+    Month,
+}
+
+impl CalendarPeriod {
+    pub fn label(&self) -> &'static str {
+        match self {
+            CalendarPeriod::Day => "daily",
+            CalendarPeriod::Week => "weekly",
+            CalendarPeriod::Month => "monthly",
+        }
+    }
+
+    /// Grouping key for `date`: the date itself, its ISO week or its month.
+    fn key(&self, date: NaiveDate) -> (i32, u32, u32) {
+        match self {
+            CalendarPeriod::Day => (date.year(), date.month(), date.day()),
+            CalendarPeriod::Week => (date.iso_week().year(), date.iso_week().week(), 0),
+            CalendarPeriod::Month => (date.year(), date.month(), 0),
+        }
+    }
+}
+
+/// One trading day of bars, identified by its Eastern-time calendar date.
+/// When grouped by week or month, `date` is the first trading date of the span.
+#[derive(Debug, Clone)]
+pub struct Session {
+    pub date: NaiveDate,
+    /// Index range of the session's bars in the slice it was built from.
+    pub range: Range<usize>,
+}
+
+impl Session {
+    pub fn bars<'a>(&self, bars: &'a [Bar]) -> &'a [Bar] {
+        &bars[self.range.clone()]
+    }
+}
+
+/// Split a time-sorted bar series into per-date sessions.
+pub fn split_sessions(bars: &[Bar]) -> Vec<Session> {
+    split_periods(bars, CalendarPeriod::Day)
+}
+
+/// Split a time-sorted bar series into consecutive calendar periods.
+pub fn split_periods(bars: &[Bar], period: CalendarPeriod) -> Vec<Session> {
+    let mut sessions: Vec<Session> = Vec::new();
+    for (idx, bar) in bars.iter().enumerate() {
+        let date = bar.timestamp.date_naive();
+        match sessions.last_mut() {
+            Some(session) if period.key(session.date) == period.key(date) => {
+                session.range.end = idx + 1
+            }
+            _ => sessions.push(Session {
+                date,
+                range: idx..idx + 1,
+            }),
+        }
+    }
+    sessions
+}
@@ -0,0 +1,350 @@
+use std::f64::consts::PI;
+
+use clap::ValueEnum;
+
+use crate::analysis::atr::compute_causal_atr;
+use crate::analysis::fft::dft;
+use crate::analysis::stats::{evaluate_levels, EvaluationSettings};
+use crate::data::{Bar, Level, PerformanceStats, Significance};
+
+/// Baseline a level's performance is compared against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum NullModel {
+    /// Levels at random prices on the same side, at a similar distance from
+    /// the current price and with the same zone, scored on the real bars.
+    RandomLevels,
+    /// The real levels scored on paths stitched from random blocks of bars.
+    BlockBootstrap,
+    /// The real levels scored on paths with the spectrum of close-to-close
+    /// changes kept and their phases randomised.
+    PhaseRandomized,
+}
+
+impl NullModel {
+    pub fn label(&self) -> &'static str {
+        match self {
+            NullModel::RandomLevels => "random levels",
+            NullModel::BlockBootstrap => "block-bootstrapped paths",
+            NullModel::PhaseRandomized => "phase-randomised paths",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SignificanceSettings {
+    pub model: NullModel,
+    /// Null levels per level, or surrogate paths.
+    pub draws: usize,
+    /// Bars per block for the block bootstrap.
+    pub block_length: usize,
+    /// False discovery rate for the Benjamini-Hochberg flags.
+    pub fdr: f64,
+    pub seed: u64,
+    /// ATR period for the surrogate paths, which are scored on their own
+    /// causal ATR rather than the real one.
+    pub atr_period: usize,
+}
+
+/// SplitMix64 generator; keeps null draws reproducible from `seed`.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform on `[0, 1)`.
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Uniform index below `n`, which must be positive.
+    fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+}
+
+/// Test each level's hit rate and trade expectancy on `bars` against the
+/// null model and store the result in `level.significance`.
+///
+/// Observed and null statistics are both computed here with `evaluation`,
+/// so levels whose stored performance was pooled elsewhere (pivots) are
+/// compared like for like. p-values are one-sided, `(1 + k) / (1 + n)` for
+/// `k` of `n` null draws at least as good, and the flags control the false
+/// discovery rate over all levels with at least one test. Untested levels
+/// get no result. `atr` is aligned with `bars` and serves the observed
+/// statistics and the random-level null; bootstrapped and phase-randomised
+/// paths are scored on their own causal ATR.
+pub fn test_significance(
+    levels: &mut [Level],
+    bars: &[Bar],
+    atr: &[f64],
+    evaluation: &EvaluationSettings,
+    settings: &SignificanceSettings,
+) {
+    if bars.len() < 2 || levels.is_empty() {
+        return;
+    }
+    let mut rng = SplitMix64(settings.seed);
+    let observed = evaluate_levels(levels.to_vec(), bars, atr, evaluation);
+    let mut nulls: Vec<Vec<PerformanceStats>> = vec![Vec::new(); levels.len()];
+    match settings.model {
+        NullModel::RandomLevels => {
+            let (low, high) = bars.iter().fold((f64::MAX, f64::MIN), |(lo, hi), bar| {
+                (lo.min(bar.low), hi.max(bar.high))
+            });
+            let current_price = bars[bars.len() - 1].close;
+            for (stats, level) in nulls.iter_mut().zip(levels.iter()) {
+                let draws: Vec<Level> = (0..settings.draws)
+                    .map(|_| random_level(level, (low, high), current_price, &mut rng))
+                    .collect();
+                *stats = evaluate_levels(draws, bars, atr, evaluation)
+                    .into_iter()
+                    .map(|level| level.performance)
+                    .collect();
+            }
+        }
+        NullModel::BlockBootstrap | NullModel::PhaseRandomized => {
+            for _ in 0..settings.draws {
+                let path = if settings.model == NullModel::BlockBootstrap {
+                    block_bootstrap(bars, settings.block_length, &mut rng)
+                } else {
+                    phase_randomized(bars, &mut rng)
+                };
+                let path_atr = compute_causal_atr(&path, settings.atr_period);
+                let scored = evaluate_levels(levels.to_vec(), &path, &path_atr, evaluation);
+                for (stats, level) in nulls.iter_mut().zip(scored) {
+                    stats.push(level.performance);
+                }
+            }
+        }
+    }
+
+    let mut results: Vec<Option<Significance>> = observed
+        .iter()
+        .zip(&nulls)
+        .map(|(level, nulls)| {
+            let stats = &level.performance;
+            if stats.tests == 0 {
+                return None;
+            }
+            let null_hits: Vec<f64> = nulls
+                .iter()
+                .filter(|null| null.tests > 0)
+                .map(|null| null.hit_rate)
+                .collect();
+            let null_expectancies: Vec<f64> = nulls
+                .iter()
+                .filter(|null| null.trades.trades > 0)
+                .map(|null| null.trades.expectancy())
+                .collect();
+            Some(Significance {
+                hit_rate: stats.hit_rate,
+                expectancy: stats.trades.expectancy(),
+                null_hit_rate: mean(&null_hits),
+                null_expectancy: mean(&null_expectancies),
+                hit_rate_p: empirical_p_value(stats.hit_rate, &null_hits),
+                expectancy_p: empirical_p_value(stats.trades.expectancy(), &null_expectancies),
+                draws: null_hits.len(),
+                hit_rate_significant: false,
+                expectancy_significant: false,
+            })
+        })
+        .collect();
+
+    let tested: Vec<&mut Significance> = results.iter_mut().flatten().collect();
+    let hit_flags = benjamini_hochberg(
+        &tested.iter().map(|s| s.hit_rate_p).collect::<Vec<_>>(),
+        settings.fdr,
+    );
+    let expectancy_flags = benjamini_hochberg(
+        &tested.iter().map(|s| s.expectancy_p).collect::<Vec<_>>(),
+        settings.fdr,
+    );
+    for ((result, hit), expectancy) in tested.into_iter().zip(hit_flags).zip(expectancy_flags) {
+        result.hit_rate_significant = hit;
+        result.expectancy_significant = expectancy;
+    }
+    for (level, result) in levels.iter_mut().zip(results) {
+        level.significance = result;
+    }
+}
+
+/// Null level with the same zone and side as `level`, at a distance from
+/// the current price drawn uniformly between half and one and a half times
+/// its own, kept inside the traded `range` (the whole range when that
+/// interval misses it).
+fn random_level(
+    level: &Level,
+    range: (f64, f64),
+    current_price: f64,
+    rng: &mut SplitMix64,
+) -> Level {
+    let offset = level.price - current_price;
+    let (mut lo, mut hi) = if offset >= 0.0 {
+        (current_price + 0.5 * offset, current_price + 1.5 * offset)
+    } else {
+        (current_price + 1.5 * offset, current_price + 0.5 * offset)
+    };
+    lo = lo.max(range.0);
+    hi = hi.min(range.1);
+    if hi <= lo {
+        (lo, hi) = range;
+    }
+    let price = lo + rng.next_f64() * (hi - lo);
+    let mut null = Level::new(
+        price,
+        (
+            price - (level.price - level.zone_low),
+            price + (level.zone_high - level.price),
+        ),
+        0.0,
+        level.source,
+        String::new(),
+        current_price,
+    );
+    null.level_type = level.level_type;
+    null
+}
+
+/// Path of the same length as `bars` built from circular blocks of
+/// `block_length` bars, each shifted so it opens from the previous block's
+/// close with its original gap. Timestamps follow the real bars.
+fn block_bootstrap(bars: &[Bar], block_length: usize, rng: &mut SplitMix64) -> Vec<Bar> {
+    let n = bars.len();
+    let block_length = block_length.clamp(1, n);
+    let mut path: Vec<Bar> = Vec::with_capacity(n);
+    let mut close = bars[0].open;
+    while path.len() < n {
+        let start = rng.below(n);
+        for offset in 0..block_length.min(n - path.len()) {
+            let idx = (start + offset) % n;
+            let prior = if idx == 0 {
+                bars[0].open
+            } else {
+                bars[idx - 1].close
+            };
+            let bar = shifted(&bars[idx], &bars[path.len()], close - prior);
+            close = bar.close;
+            path.push(bar);
+        }
+    }
+    path
+}
+
+/// Path whose close-to-close changes keep the amplitude spectrum of the real
+/// ones with uniformly random phases. The transform runs on exactly the
+/// `n - 1` changes, so the surrogate keeps their variance and
+/// autocorrelation; an even length keeps its Nyquist term. Each bar keeps
+/// its open, high and low relative to its close.
+fn phase_randomized(bars: &[Bar], rng: &mut SplitMix64) -> Vec<Bar> {
+    let changes: Vec<f64> = bars
+        .windows(2)
+        .map(|pair| pair[1].close - pair[0].close)
+        .collect();
+    let drift = mean(&changes);
+    let size = changes.len();
+    let mut re: Vec<f64> = changes.iter().map(|change| change - drift).collect();
+    let mut im = vec![0.0; size];
+    dft(&mut re, &mut im, false);
+    for k in 1..size.div_ceil(2) {
+        let amplitude = re[k].hypot(im[k]);
+        let phase = 2.0 * PI * rng.next_f64();
+        re[k] = amplitude * phase.cos();
+        im[k] = amplitude * phase.sin();
+        re[size - k] = re[k];
+        im[size - k] = -im[k];
+    }
+    dft(&mut re, &mut im, true);
+
+    let mut close = bars[0].close;
+    let mut path = Vec::with_capacity(bars.len());
+    path.push(bars[0].clone());
+    for (bar, change) in bars.iter().skip(1).zip(&re) {
+        close += change + drift;
+        path.push(shifted(bar, bar, close - bar.close));
+    }
+    path
+}
+
+/// `bar` moved by `delta` in price and placed at `slot`'s timestamp.
+fn shifted(bar: &Bar, slot: &Bar, delta: f64) -> Bar {
+    Bar {
+        timestamp: slot.timestamp,
+        open: bar.open + delta,
+        high: bar.high + delta,
+        low: bar.low + delta,
+        close: bar.close + delta,
+        volume: bar.volume,
+    }
+}
+
+fn empirical_p_value(observed: f64, nulls: &[f64]) -> f64 {
+    let at_least = nulls.iter().filter(|&&null| null >= observed).count();
+    (1 + at_least) as f64 / (1 + nulls.len()) as f64
+}
+
+/// Benjamini-Hochberg step-up: flags the `k` smallest p-values, where `k` is
+/// the largest rank with `p_(k) <= k q / m`.
+fn benjamini_hochberg(p_values: &[f64], fdr: f64) -> Vec<bool> {
+    let m = p_values.len();
+    let mut order: Vec<usize> = (0..m).collect();
+    order.sort_by(|&a, &b| {
+        p_values[a]
+            .partial_cmp(&p_values[b])
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    let cutoff = order
+        .iter()
+        .enumerate()
+        .filter(|(rank, &idx)| p_values[idx] <= (rank + 1) as f64 * fdr / m as f64)
+        .map(|(rank, _)| rank + 1)
+        .next_back()
+        .unwrap_or(0);
+    let mut flags = vec![false; m];
+    for &idx in &order[..cutoff] {
+        flags[idx] = true;
+    }
+    flags
+}
+
+fn mean(values: &[f64]) -> f64 {
+    if values.is_empty() {
+        0.0
+    } else {
+        values.iter().sum::<f64>() / values.len() as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn benjamini_hochberg_flags() {
+        // Rank thresholds at q = 0.1 and m = 4 are 0.025, 0.05, 0.075, 0.1.
+        assert_eq!(
+            benjamini_hochberg(&[0.01, 0.04, 0.03, 0.20], 0.1),
+            vec![true, true, true, false]
+        );
+        // Step-up: the smallest p fails its own threshold but is flagged
+        // with the larger ranks that pass.
+        assert_eq!(
+            benjamini_hochberg(&[0.045, 0.03, 0.9, 0.04], 0.1),
+            vec![true, true, false, true]
+        );
+        assert_eq!(benjamini_hochberg(&[0.5, 0.2], 0.1), vec![false, false]);
+        assert!(benjamini_hochberg(&[], 0.1).is_empty());
+    }
+
+    #[test]
+    fn empirical_p_value_counts_the_observation() {
+        // Two of four nulls are at least as large: (1 + 2) / (1 + 4).
+        assert!((empirical_p_value(2.0, &[1.0, 2.0, 3.0, 0.5]) - 0.6).abs() < 1e-12);
+        assert_eq!(empirical_p_value(1.0, &[]), 1.0);
+    }
+}
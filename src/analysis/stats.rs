@@ -1,11 +1,52 @@
-use crate::data::{Bar, Level, LevelType, PerformanceStats};
+use crate::data::{Bar, Level, LevelType, PerformanceStats, TouchOutcome};
 
+/// Simple fade trade taken at every touch, long when price approaches from
+/// above and short when it approaches from below.
+#[derive(Debug, Clone, Copy)]
+pub struct TradeSettings {
+    /// Stop distance beyond the far zone edge, in ATR multiples.
+    pub stop_atr: f64,
+    /// Target distance from the entry, in ATR multiples.
+    pub target_atr: f64,
+    /// Bars after entry before the trade is closed at market.
+    pub time_stop: usize,
+    /// Price slippage on the entry and on stop and time exits.
+    pub slippage: f64,
+    /// Round-trip commission in price points.
+    pub commission: f64,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct EvaluationSettings {
+    /// Bars after the start of a touch over which the reaction is measured.
+    pub reaction_lookahead: usize,
+    /// Move away from the level, in ATR multiples, that counts as a reaction.
+    pub reaction_move_atr: f64,
+    /// Distance beyond the zone edge, in ATR multiples, that a bar must clear
+    /// before the next entry counts as a new touch.
+    pub reset_atr: f64,
+    /// Close beyond the level, in ATR multiples, that counts as a break.
+    pub breakout_atr: f64,
+    pub trade: TradeSettings,
+}
+
+/// Score each level on its touch episodes in `bars`.
+///
+/// A touch starts on the first bar overlapping the zone after a bar that lay
+/// entirely outside it, and lasts until a bar clears the zone by
+/// `reset_atr` ATR; re-entries before then belong to the same touch. A level
+/// already inside the zone on the first bar has no observed entry, so that
+/// episode is skipped. Each touch is one test, with the reaction measured
+/// from its first bar, its outcome classified by `classify_touch` and a trade
+/// simulated by `simulate_trade`. The hit rate and reactions measure the
+/// move from the level price in the direction of its support or resistance
+/// role; the outcome counts of each approach side also record the move back
+/// to that side beyond the near zone edge, which agrees with the bounces.
 pub fn evaluate_levels(
     mut levels: Vec<Level>,
     bars: &[Bar],
     atr: &[f64],
-    reaction_lookahead: usize,
-    reaction_move_atr: f64,
+    settings: &EvaluationSettings,
 ) -> Vec<Level> {
     if bars.is_empty() {
         return levels;
@@ -17,29 +58,95 @@ pub fn evaluate_levels(
     };
 
     for level in &mut levels {
-        let mut tests = 0usize;
+        let mut stats = PerformanceStats::empty();
         let mut hits = 0usize;
-        let mut touches = 0usize;
         let mut total_reaction = 0.0;
-        let mut max_reaction: f64 = 0.0;
         let mut total_reaction_bars = 0.0;
+        let mut total_adverse = 0.0;
+        let mut total_bars_to_adverse = 0.0;
+        let mut armed = !level.overlaps(bars[0].low, bars[0].high);
+        let mut in_touch = false;
 
         for (idx, bar) in bars.iter().enumerate() {
-            let tolerance = level.confidence_band;
-            let touched = bar.low <= level.price + tolerance && bar.high >= level.price - tolerance;
-            if !touched {
+            let atr_ref = atr.get(idx).copied().unwrap_or(mean_atr).max(1e-6);
+            if !level.overlaps(bar.low, bar.high) {
+                let reset = settings.reset_atr * atr_ref;
+                if bar.low >= level.zone_high + reset || bar.high <= level.zone_low - reset {
+                    armed = true;
+                    in_touch = false;
+                }
                 continue;
             }
-            tests += 1;
-            touches += 1;
-            let atr_ref = atr.get(idx).copied().unwrap_or(mean_atr).max(1e-6);
+            if in_touch {
+                stats.bars_in_zone += 1;
+                continue;
+            }
+            if !armed {
+                continue;
+            }
+            armed = false;
+            in_touch = true;
+            stats.touches += 1;
+            stats.bars_in_zone += 1;
+            let from_above = bars[idx - 1].low > level.zone_high;
+            if from_above {
+                stats.from_above += 1;
+            } else {
+                stats.from_below += 1;
+            }
+
+            let end = (idx + settings.reaction_lookahead + 1).min(bars.len());
+            if end <= idx + 1 {
+                continue;
+            }
+            let outcome = classify_touch(level, &bars[idx..end], from_above, atr_ref, settings);
+            if from_above {
+                stats.outcomes_above.record(outcome);
+            } else {
+                stats.outcomes_below.record(outcome);
+            }
+
+            let reaction_distance = settings.reaction_move_atr * atr_ref;
+            let approach_move = bars[idx + 1..end]
+                .iter()
+                .map(|forward_bar| {
+                    if from_above {
+                        forward_bar.high - level.zone_high
+                    } else {
+                        level.zone_low - forward_bar.low
+                    }
+                })
+                .fold(0.0, f64::max);
+            let counts = if from_above {
+                &mut stats.outcomes_above
+            } else {
+                &mut stats.outcomes_below
+            };
+            counts.record_reaction(approach_move, approach_move >= reaction_distance);
+
+            let stop = stop_price(level, from_above, atr_ref, &settings.trade);
+            let (adverse, bars_to_adverse) =
+                adverse_excursion(level, &bars[idx..end], from_above, stop);
+            total_adverse += adverse;
+            stats.max_adverse_excursion = stats.max_adverse_excursion.max(adverse);
+            if let Some(bars_to_adverse) = bars_to_adverse {
+                stats.adverse_hits += 1;
+                total_bars_to_adverse += bars_to_adverse as f64;
+            }
+            let trade_end = (idx + settings.trade.time_stop + 1).min(bars.len());
+            let (pnl, r) = simulate_trade(
+                level,
+                &bars[idx..trade_end],
+                from_above,
+                atr_ref,
+                &settings.trade,
+            );
+            stats.trades.record(pnl, r);
 
             let mut best_move = 0.0;
             let mut bars_to_best = 0usize;
             let mut success = false;
-            let end = (idx + reaction_lookahead + 1).min(bars.len());
-            for forward_idx in idx + 1..end {
-                let forward_bar = &bars[forward_idx];
+            for (forward_idx, forward_bar) in bars.iter().enumerate().take(end).skip(idx + 1) {
                 let movement = match level.level_type {
                     LevelType::Support => forward_bar.high - level.price,
                     LevelType::Resistance => level.price - forward_bar.low,
@@ -48,33 +155,278 @@ pub fn evaluate_levels(
                     best_move = movement;
                     bars_to_best = forward_idx - idx;
                 }
-                if movement >= reaction_move_atr * atr_ref {
+                if movement >= reaction_distance {
                     success = true;
                 }
             }
+            if stats.tests == 0 {
+                stats.first_tests = 1;
+                stats.first_hits = usize::from(success);
+                stats.first_avg_reaction = best_move;
+            }
+            stats.tests += 1;
             if success {
                 hits += 1;
             }
             total_reaction += best_move;
-            max_reaction = max_reaction.max(best_move);
+            stats.max_favorable_excursion = stats.max_favorable_excursion.max(best_move);
             total_reaction_bars += bars_to_best as f64;
         }
 
-        let performance = if tests > 0 {
-            PerformanceStats {
-                touches,
-                tests,
-                hit_rate: hits as f64 / tests as f64,
-                avg_reaction: total_reaction / tests as f64,
-                max_favorable_excursion: max_reaction,
-                avg_reaction_bars: total_reaction_bars / tests as f64,
+        if stats.tests > 0 {
+            let tests = stats.tests as f64;
+            stats.hit_rate = hits as f64 / tests;
+            stats.avg_reaction = total_reaction / tests;
+            stats.avg_reaction_bars = total_reaction_bars / tests;
+            stats.avg_adverse_excursion = total_adverse / tests;
+        }
+        if stats.adverse_hits > 0 {
+            stats.avg_bars_to_adverse = total_bars_to_adverse / stats.adverse_hits as f64;
+        }
+        level.performance = stats;
+    }
+
+    levels
+}
+
+/// Classify a touch from its entry bar and the lookahead bars after it.
+///
+/// Distances are measured from the zone edges, so wide zones are not
+/// counted as bounces or breaks while price is still inside them. A close
+/// beyond the far edge by `breakout_atr` ATR is a break, which becomes a
+/// false breakout if a later close is back on the approach side of the level
+/// price. Without a break, a move of `reaction_move_atr` ATR beyond the near
+/// edge is a bounce. The first of the two decides the outcome; the entry bar
+/// can break but not bounce, since it arrives from that side.
+fn classify_touch(
+    level: &Level,
+    window: &[Bar],
+    from_above: bool,
+    atr_ref: f64,
+    settings: &EvaluationSettings,
+) -> TouchOutcome {
+    let (direction, near, far) = if from_above {
+        (1.0, level.zone_high, level.zone_low)
+    } else {
+        (-1.0, level.zone_low, level.zone_high)
+    };
+    let break_distance = settings.breakout_atr * atr_ref;
+    let bounce_distance = settings.reaction_move_atr * atr_ref;
+    let mut broken = false;
+    for (offset, bar) in window.iter().enumerate() {
+        if broken {
+            if direction * (bar.close - level.price) > 0.0 {
+                return TouchOutcome::FalseBreakout;
             }
+            continue;
+        }
+        if direction * (far - bar.close) >= break_distance {
+            broken = true;
+            continue;
+        }
+        let extreme = if from_above { bar.high } else { bar.low };
+        if offset > 0 && direction * (extreme - near) >= bounce_distance {
+            return TouchOutcome::Bounce;
+        }
+    }
+    if broken {
+        TouchOutcome::Breakout
+    } else {
+        TouchOutcome::Chop
+    }
+}
+
+/// Stop price for a fade of `level`: beyond the far zone edge by `stop_atr`
+/// ATR.
+fn stop_price(level: &Level, from_above: bool, atr_ref: f64, trade: &TradeSettings) -> f64 {
+    if from_above {
+        level.zone_low - trade.stop_atr * atr_ref
+    } else {
+        level.zone_high + trade.stop_atr * atr_ref
+    }
+}
+
+/// Largest move against the approach side from the near zone edge over the
+/// entry bar and the lookahead, and the bars from entry until `stop` traded.
+fn adverse_excursion(
+    level: &Level,
+    window: &[Bar],
+    from_above: bool,
+    stop: f64,
+) -> (f64, Option<usize>) {
+    let mut worst: f64 = 0.0;
+    let mut bars_to_stop = None;
+    for (offset, bar) in window.iter().enumerate() {
+        let (adverse, stopped) = if from_above {
+            (level.zone_high - bar.low, bar.low <= stop)
         } else {
-            PerformanceStats::empty()
+            (bar.high - level.zone_low, bar.high >= stop)
         };
+        worst = worst.max(adverse);
+        if stopped && bars_to_stop.is_none() {
+            bars_to_stop = Some(offset);
+        }
+    }
+    (worst, bars_to_stop)
+}
 
-        level.performance = performance;
+/// Net result in price points and in R of a fade entered at the near zone
+/// edge on the entry bar of `window`.
+///
+/// The stop is checked from the entry bar and the target from the next bar;
+/// when both trade in the same bar the stop is assumed to fill first. The
+/// trade is closed at the last close of `window` if neither is reached.
+/// Slippage worsens the entry and the stop and time exits, but not the
+/// target, which rests as a limit order.
+fn simulate_trade(
+    level: &Level,
+    window: &[Bar],
+    from_above: bool,
+    atr_ref: f64,
+    trade: &TradeSettings,
+) -> (f64, f64) {
+    let direction = if from_above { 1.0 } else { -1.0 };
+    let near = if from_above {
+        level.zone_high
+    } else {
+        level.zone_low
+    };
+    let entry = near + direction * trade.slippage;
+    let stop = stop_price(level, from_above, atr_ref, trade);
+    let target = entry + direction * trade.target_atr * atr_ref;
+
+    let mut exit = None;
+    for (offset, bar) in window.iter().enumerate() {
+        let (adverse, favorable) = if from_above {
+            (bar.low, bar.high)
+        } else {
+            (bar.high, bar.low)
+        };
+        if direction * (adverse - stop) <= 0.0 {
+            exit = Some(stop - direction * trade.slippage);
+            break;
+        }
+        if offset > 0 && direction * (favorable - target) >= 0.0 {
+            exit = Some(target);
+            break;
+        }
     }
+    let exit = exit.unwrap_or_else(|| {
+        window.last().map_or(entry, |bar| bar.close) - direction * trade.slippage
+    });
+    let pnl = direction * (exit - entry) - trade.commission;
+    let risk = (direction * (entry - stop)).max(1e-9);
+    (pnl, pnl / risk)
+}
 
-    levels
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use chrono_tz::America::New_York;
+
+    use super::*;
+    use crate::data::LevelSource;
+
+    fn bar(minute: u32, high: f64, low: f64, close: f64) -> Bar {
+        Bar {
+            timestamp: New_York
+                .with_ymd_and_hms(2024, 3, 4, 10, minute, 0)
+                .unwrap(),
+            open: close,
+            high,
+            low,
+            close,
+            volume: 1.0,
+        }
+    }
+
+    /// Support at 100 with a 99..101 zone, above the last price.
+    fn support() -> Level {
+        Level::new(
+            100.0,
+            (99.0, 101.0),
+            1.0,
+            LevelSource::Density,
+            String::new(),
+            105.0,
+        )
+    }
+
+    fn settings() -> EvaluationSettings {
+        EvaluationSettings {
+            reaction_lookahead: 5,
+            reaction_move_atr: 1.0,
+            reset_atr: 0.5,
+            breakout_atr: 0.5,
+            trade: TradeSettings {
+                stop_atr: 0.5,
+                target_atr: 2.0,
+                time_stop: 5,
+                slippage: 0.25,
+                commission: 0.5,
+            },
+        }
+    }
+
+    #[test]
+    fn classify_touch_outcomes() {
+        let level = support();
+        let settings = settings();
+        let classify = |window: &[Bar]| classify_touch(&level, window, true, 1.0, &settings);
+
+        // A move of 1 ATR above the near edge after the entry bar.
+        let bounce = [bar(0, 101.5, 100.5, 101.0), bar(1, 102.0, 100.8, 101.8)];
+        assert_eq!(classify(&bounce), TouchOutcome::Bounce);
+
+        // The entry bar arrives from above, so its high is no bounce.
+        let chop = [bar(0, 103.0, 100.0, 100.0), bar(1, 101.5, 99.5, 100.0)];
+        assert_eq!(classify(&chop), TouchOutcome::Chop);
+
+        // A close 0.6 ATR below the far edge that stays below the level.
+        let breakout = [bar(0, 101.0, 98.2, 98.4), bar(1, 99.5, 96.5, 97.0)];
+        assert_eq!(classify(&breakout), TouchOutcome::Breakout);
+
+        // The same break followed by a close back above the level price.
+        let false_breakout = [bar(0, 101.0, 98.2, 98.4), bar(1, 101.0, 98.0, 100.5)];
+        assert_eq!(classify(&false_breakout), TouchOutcome::FalseBreakout);
+    }
+
+    #[test]
+    fn simulate_trade_exits() {
+        let level = support();
+        let trade = settings().trade;
+        let simulate = |window: &[Bar], from_above: bool| {
+            simulate_trade(&level, window, from_above, 1.0, &trade)
+        };
+        // Long entry at 101.25 with the stop at 98.5 risks 2.75.
+        let risk = 2.75;
+
+        // Target at 103.25, reached on the bar after entry.
+        let (pnl, r) = simulate(
+            &[bar(0, 101.5, 100.5, 101.0), bar(1, 103.5, 101.0, 103.0)],
+            true,
+        );
+        assert!((pnl - 1.5).abs() < 1e-12, "target pnl {pnl}");
+        assert!((r - 1.5 / risk).abs() < 1e-12, "target r {r}");
+
+        // Stop filled at 98.25 after slippage.
+        let (pnl, r) = simulate(
+            &[bar(0, 101.5, 100.5, 101.0), bar(1, 101.0, 98.0, 98.5)],
+            true,
+        );
+        assert!((pnl + 3.5).abs() < 1e-12, "stop pnl {pnl}");
+        assert!((r + 3.5 / risk).abs() < 1e-12, "stop r {r}");
+
+        // The target only counts after the entry bar; time exit at 102.25.
+        let (pnl, _) = simulate(
+            &[bar(0, 104.0, 100.5, 101.0), bar(1, 102.8, 101.0, 102.5)],
+            true,
+        );
+        assert!((pnl - 0.5).abs() < 1e-12, "time exit pnl {pnl}");
+
+        // Short entry at 98.75 from below, target at 96.75.
+        let (pnl, r) = simulate(&[bar(0, 99.5, 98.5, 99.0), bar(1, 99.0, 96.5, 97.0)], false);
+        assert!((pnl - 1.5).abs() < 1e-12, "short pnl {pnl}");
+        assert!((r - 1.5 / risk).abs() < 1e-12, "short r {r}");
+    }
 }
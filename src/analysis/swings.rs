@@ -1,5 +1,33 @@
 use crate::data::{Bar, SwingPoint, SwingType};
 
+/// Move between two consecutive swing points.
+#[derive(Debug, Clone)]
+pub struct SwingLeg {
+    pub start: SwingPoint,
+    pub end: SwingPoint,
+    /// Absolute price move in multiples of the ATR at the ending swing.
+    pub size_atr: f64,
+}
+
+/// The `count` largest legs between consecutive swings, largest first.
+pub fn largest_legs(swings: &[SwingPoint], count: usize) -> Vec<SwingLeg> {
+    let mut legs: Vec<SwingLeg> = swings
+        .windows(2)
+        .map(|pair| SwingLeg {
+            start: pair[0].clone(),
+            end: pair[1].clone(),
+            size_atr: (pair[1].price - pair[0].price).abs() / pair[1].atr.max(1e-9),
+        })
+        .collect();
+    legs.sort_by(|a, b| {
+        b.size_atr
+            .partial_cmp(&a.size_atr)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    legs.truncate(count);
+    legs
+}
+
 /// Detect swing highs and lows using an ATR-governed zig-zag algorithm.
 pub fn detect_swings(
     bars: &[Bar],
@@ -15,7 +43,7 @@ pub fn detect_swings(
     let mut last_type = Some(SwingType::Low);
     let mut last_index = 0usize;
     let mut last_price = bars[0].low;
-    let initial_atr = atr.get(0).copied().unwrap_or(0.0);
+    let initial_atr = atr.first().copied().unwrap_or(0.0);
     swings.push(SwingPoint {
         index: 0,
         bar: bars[0].clone(),
@@ -0,0 +1,391 @@
+use crate::analysis::peaks::{local_maxima, topographic_prominence};
+use crate::analysis::sessions::split_sessions;
+use crate::analysis::stats::{evaluate_levels, EvaluationSettings};
+use crate::data::{Bar, Level, LevelSource};
+
+/// Volume-at-price histogram on a tick grid.
+#[derive(Debug, Clone)]
+pub struct VolumeProfile {
+    pub tick_size: f64,
+    /// Price of the first bin; every bin sits on a multiple of `tick_size`.
+    pub origin: f64,
+    pub volumes: Vec<f64>,
+    pub poc: f64,
+    pub value_area_low: f64,
+    pub value_area_high: f64,
+}
+
+impl VolumeProfile {
+    pub fn price_at(&self, idx: usize) -> f64 {
+        self.origin + self.tick_size * idx as f64
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VolumeNodeKind {
+    High,
+    Low,
+}
+
+/// High- or low-volume node found on the smoothed profile.
+#[derive(Debug, Clone)]
+pub struct VolumeNode {
+    pub kind: VolumeNodeKind,
+    pub price: f64,
+    pub zone_low: f64,
+    pub zone_high: f64,
+    /// Smoothed volume at the node relative to the smoothed maximum.
+    pub relative_volume: f64,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct VolumeProfileSettings {
+    pub tick_size: f64,
+    /// Share of total volume enclosed by the value area (0.7 by convention).
+    pub value_area: f64,
+    /// Number of most recent sessions that get their own POC and value area.
+    pub sessions: usize,
+    /// Moving-average window in ticks used before searching for HVN/LVN.
+    pub smoothing_ticks: usize,
+    /// Half-width of the zone around POC and value-area edges.
+    pub band: f64,
+    pub evaluation: EvaluationSettings,
+}
+
+/// Spread each bar's volume evenly across the ticks between its low and high.
+///
+/// Bars with `high == low` put all of their volume on a single tick, so a
+/// tick-level export loaded as bars produces an exact traded-volume profile.
+/// Bars with non-finite prices or a high below their low are skipped.
+pub fn build_volume_profile(
+    bars: &[Bar],
+    tick_size: f64,
+    value_area: f64,
+) -> Option<VolumeProfile> {
+    if bars.is_empty() || !tick_size.is_finite() || tick_size <= 0.0 {
+        return None;
+    }
+    let to_tick = |price: f64| (price / tick_size + 1e-9).floor() as i64;
+    let valid = |bar: &&Bar| bar.low.is_finite() && bar.high.is_finite() && bar.low <= bar.high;
+    let low_tick = bars
+        .iter()
+        .filter(valid)
+        .map(|bar| to_tick(bar.low))
+        .min()?;
+    let high_tick = bars
+        .iter()
+        .filter(valid)
+        .map(|bar| to_tick(bar.high))
+        .max()?;
+    if high_tick < low_tick {
+        return None;
+    }
+
+    let mut volumes = vec![0.0; (high_tick - low_tick + 1) as usize];
+    for bar in bars.iter().filter(valid) {
+        if !(bar.volume.is_finite() && bar.volume > 0.0) {
+            continue;
+        }
+        let start = (to_tick(bar.low) - low_tick) as usize;
+        let end = (to_tick(bar.high) - low_tick) as usize;
+        let share = bar.volume / (end - start + 1) as f64;
+        for volume in &mut volumes[start..=end] {
+            *volume += share;
+        }
+    }
+    let total_volume: f64 = volumes.iter().sum();
+    if total_volume <= 0.0 {
+        return None;
+    }
+
+    let origin = low_tick as f64 * tick_size;
+    let poc_idx = point_of_control(&volumes);
+    let (lo, hi) = expand_value_area(&volumes, poc_idx, value_area);
+
+    Some(VolumeProfile {
+        tick_size,
+        origin,
+        poc: origin + tick_size * poc_idx as f64,
+        value_area_low: origin + tick_size * lo as f64,
+        value_area_high: origin + tick_size * hi as f64,
+        volumes,
+    })
+}
+
+/// Index of the largest bin; ties go to the bin closest to the middle of the range.
+pub fn point_of_control(values: &[f64]) -> usize {
+    let middle = values.len().saturating_sub(1) as f64 / 2.0;
+    (0..values.len())
+        .max_by(|&a, &b| {
+            values[a]
+                .partial_cmp(&values[b])
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| {
+                    let da = (a as f64 - middle).abs();
+                    let db = (b as f64 - middle).abs();
+                    db.partial_cmp(&da).unwrap_or(std::cmp::Ordering::Equal)
+                })
+        })
+        .unwrap_or(0)
+}
+
+/// Grow the value area from the POC one price at a time towards the heavier
+/// neighbour until it holds `fraction` of the total. Returns inclusive bin bounds.
+pub fn expand_value_area(values: &[f64], poc_idx: usize, fraction: f64) -> (usize, usize) {
+    let total: f64 = values.iter().sum();
+    let target = total * fraction.clamp(0.0, 1.0);
+    let (mut lo, mut hi) = (poc_idx, poc_idx);
+    let mut enclosed = values[poc_idx];
+    while enclosed < target && (lo > 0 || hi + 1 < values.len()) {
+        let above = values.get(hi + 1).copied();
+        let below = if lo > 0 { Some(values[lo - 1]) } else { None };
+        match (above, below) {
+            (Some(up), Some(down)) if up >= down => {
+                hi += 1;
+                enclosed += up;
+            }
+            (_, Some(down)) => {
+                lo -= 1;
+                enclosed += down;
+            }
+            (Some(up), None) => {
+                hi += 1;
+                enclosed += up;
+            }
+            (None, None) => break,
+        }
+    }
+    (lo, hi)
+}
+
+/// Locate high-volume nodes (local maxima of at least half the smoothed
+/// maximum, away from the POC) and low-volume nodes (local minima below a
+/// quarter of the smoothed maximum that sit between heavier areas). Both must
+/// stand out from their surroundings by `NODE_MIN_PROMINENCE` of the smoothed
+/// maximum so ripples in the profile are not reported.
+pub fn find_volume_nodes(profile: &VolumeProfile, smoothing_ticks: usize) -> Vec<VolumeNode> {
+    const NODE_MIN_PROMINENCE: f64 = 0.1;
+
+    let smoothed = moving_average(&profile.volumes, smoothing_ticks.max(1));
+    let max_smoothed = smoothed.iter().copied().fold(0.0, f64::max);
+    if smoothed.len() < 3 || max_smoothed <= 0.0 {
+        return Vec::new();
+    }
+    let poc_idx = ((profile.poc - profile.origin) / profile.tick_size).round() as usize;
+    let exclusion = smoothing_ticks.max(1);
+    let min_prominence = NODE_MIN_PROMINENCE * max_smoothed;
+
+    let maxima: Vec<usize> = local_maxima(&smoothed)
+        .into_iter()
+        .filter(|&idx| {
+            smoothed[idx] >= 0.5 * max_smoothed
+                && topographic_prominence(&smoothed, idx).0 >= min_prominence
+        })
+        .collect();
+    let mut nodes: Vec<VolumeNode> = maxima
+        .iter()
+        .copied()
+        .filter(|&idx| idx.abs_diff(poc_idx) > exclusion)
+        .map(|idx| {
+            let cutoff = smoothed[idx] * 0.5;
+            let (low, high) = walk_zone(&smoothed, idx, |value| value < cutoff);
+            VolumeNode {
+                kind: VolumeNodeKind::High,
+                price: profile.price_at(idx),
+                zone_low: profile.price_at(low),
+                zone_high: profile.price_at(high),
+                relative_volume: smoothed[idx] / max_smoothed,
+            }
+        })
+        .collect();
+
+    let heavy_low = maxima.iter().copied().chain([poc_idx]).min().unwrap_or(0);
+    let heavy_high = maxima.iter().copied().chain([poc_idx]).max().unwrap_or(0);
+    let inverted: Vec<f64> = smoothed.iter().map(|value| -value).collect();
+    for idx in local_maxima(&inverted) {
+        if idx <= heavy_low
+            || idx >= heavy_high
+            || smoothed[idx] > 0.25 * max_smoothed
+            || topographic_prominence(&inverted, idx).0 < min_prominence
+        {
+            continue;
+        }
+        let ceiling = (smoothed[idx] * 2.0).max(1e-12);
+        let (low, high) = walk_zone(&smoothed, idx, |value| value > ceiling);
+        nodes.push(VolumeNode {
+            kind: VolumeNodeKind::Low,
+            price: profile.price_at(idx),
+            zone_low: profile.price_at(low),
+            zone_high: profile.price_at(high),
+            relative_volume: smoothed[idx] / max_smoothed,
+        });
+    }
+    nodes
+}
+
+/// Volume-profile levels: POC and value-area edges for the most recent
+/// sessions plus POC, value area and HVN/LVN over the whole window.
+///
+/// A session's levels are evaluated only on the bars after that session, with
+/// its last bar as context. The composite levels are built from every bar, so
+/// nothing is left to score them on and they carry no statistics. `atr` must
+/// be aligned with `bars`.
+pub fn compute_volume_profile_levels(
+    bars: &[Bar],
+    atr: &[f64],
+    settings: &VolumeProfileSettings,
+    current_price: f64,
+) -> Vec<Level> {
+    let mut levels = Vec::new();
+    let band = settings.band.max(settings.tick_size);
+
+    let sessions = split_sessions(bars);
+    for session in sessions.iter().rev().take(settings.sessions) {
+        let Some(profile) =
+            build_volume_profile(session.bars(bars), settings.tick_size, settings.value_area)
+        else {
+            continue;
+        };
+        let date = session.date.format("%Y-%m-%d");
+        let mut session_levels = Vec::new();
+        push_profile_levels(
+            &mut session_levels,
+            &profile,
+            &date.to_string(),
+            band,
+            current_price,
+        );
+        let context = session.range.end - 1;
+        levels.extend(evaluate_levels(
+            session_levels,
+            &bars[context..],
+            atr.get(context..).unwrap_or(&[]),
+            &settings.evaluation,
+        ));
+    }
+
+    if let Some(composite) = build_volume_profile(bars, settings.tick_size, settings.value_area) {
+        push_profile_levels(&mut levels, &composite, "composite", band, current_price);
+        for node in find_volume_nodes(&composite, settings.smoothing_ticks) {
+            let (name, confidence) = match node.kind {
+                VolumeNodeKind::High => ("HVN", node.relative_volume),
+                VolumeNodeKind::Low => ("LVN", 1.0 - node.relative_volume),
+            };
+            levels.push(Level::new(
+                node.price,
+                (node.zone_low, node.zone_high),
+                confidence,
+                LevelSource::VolumeProfile,
+                format!("{name} composite"),
+                current_price,
+            ));
+        }
+    }
+    levels
+}
+
+fn push_profile_levels(
+    levels: &mut Vec<Level>,
+    profile: &VolumeProfile,
+    scope: &str,
+    band: f64,
+    current_price: f64,
+) {
+    let max_volume = profile.volumes.iter().copied().fold(0.0, f64::max);
+    let relative = |price: f64| {
+        let idx = ((price - profile.origin) / profile.tick_size).round() as usize;
+        profile.volumes.get(idx).copied().unwrap_or(0.0) / max_volume.max(1e-12)
+    };
+    for (name, price) in [
+        ("POC", profile.poc),
+        ("VAH", profile.value_area_high),
+        ("VAL", profile.value_area_low),
+    ] {
+        levels.push(Level::new(
+            price,
+            (price - band, price + band),
+            relative(price),
+            LevelSource::VolumeProfile,
+            format!("{name} {scope}"),
+            current_price,
+        ));
+    }
+}
+
+/// Expand from `idx` until `stop` holds or the values turn back towards the
+/// node, returning the inclusive bin range.
+fn walk_zone(values: &[f64], idx: usize, stop: impl Fn(f64) -> bool) -> (usize, usize) {
+    let node = values[idx];
+    let moving_away = |from: f64, to: f64| (to - node).abs() >= (from - node).abs();
+    let mut low = idx;
+    while low > 0 && !stop(values[low - 1]) && moving_away(values[low], values[low - 1]) {
+        low -= 1;
+    }
+    let mut high = idx;
+    while high + 1 < values.len()
+        && !stop(values[high + 1])
+        && moving_away(values[high], values[high + 1])
+    {
+        high += 1;
+    }
+    (low, high)
+}
+
+fn moving_average(values: &[f64], window: usize) -> Vec<f64> {
+    let half = window / 2;
+    (0..values.len())
+        .map(|idx| {
+            let start = idx.saturating_sub(half);
+            let end = (idx + half + 1).min(values.len());
+            values[start..end].iter().sum::<f64>() / (end - start) as f64
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use chrono_tz::America::New_York;
+
+    use super::*;
+
+    fn print(minute: u32, price: f64, volume: f64) -> Bar {
+        Bar {
+            timestamp: New_York
+                .with_ymd_and_hms(2024, 3, 4, 10, minute, 0)
+                .unwrap(),
+            open: price,
+            high: price,
+            low: price,
+            close: price,
+            volume,
+        }
+    }
+
+    #[test]
+    fn value_area_grows_towards_the_heavier_side() {
+        let bars = [
+            print(0, 100.0, 10.0),
+            print(1, 101.0, 30.0),
+            print(2, 102.0, 50.0),
+            print(3, 103.0, 20.0),
+            print(4, 104.0, 5.0),
+        ];
+        let profile = build_volume_profile(&bars, 1.0, 0.7).unwrap();
+        assert_eq!(profile.volumes, vec![10.0, 30.0, 50.0, 20.0, 5.0]);
+        assert_eq!(profile.poc, 102.0);
+        // 50 + 30 (heavier below) = 80 < 80.5, then 20 above reaches 100.
+        assert_eq!(profile.value_area_low, 101.0);
+        assert_eq!(profile.value_area_high, 103.0);
+    }
+
+    #[test]
+    fn inverted_bars_are_skipped() {
+        let mut inverted = print(1, 103.0, 40.0);
+        inverted.low = 105.0;
+        let bars = [print(0, 100.0, 10.0), inverted, print(2, 101.0, 20.0)];
+        let profile = build_volume_profile(&bars, 1.0, 0.7).unwrap();
+        assert_eq!(profile.volumes, vec![10.0, 20.0]);
+        assert_eq!(profile.poc, 101.0);
+    }
+}
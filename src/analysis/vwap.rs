@@ -0,0 +1,217 @@
+use chrono::{DateTime, Datelike};
+use chrono_tz::Tz;
+use clap::ValueEnum;
+
+use crate::analysis::stats::{evaluate_levels, EvaluationSettings};
+use crate::analysis::swings::largest_legs;
+use crate::data::{Bar, Level, LevelSource, SwingPoint, SwingType};
+
+/// Built-in anchor families for anchored VWAPs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum VwapAnchorKind {
+    /// First bar of the most recent session.
+    Session,
+    /// First bar of the current calendar week.
+    Week,
+    /// First bar of the current calendar month.
+    Month,
+    /// Bar that set the all-time high of the loaded history.
+    Ath,
+    /// Largest swing highs and lows by leg size in ATR multiples.
+    Swings,
+}
+
+/// Bar from which a VWAP is accumulated.
+#[derive(Debug, Clone)]
+pub struct VwapAnchor {
+    pub label: String,
+    pub index: usize,
+}
+
+/// Volume-weighted average price from an anchor to the last bar.
+#[derive(Debug, Clone)]
+pub struct AnchoredVwap {
+    pub label: String,
+    pub vwap: f64,
+    /// Volume-weighted standard deviation of typical price around the VWAP.
+    pub std_dev: f64,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct VwapSettings {
+    /// Number of ±σ band pairs per VWAP (3 gives ±1σ, ±2σ, ±3σ).
+    pub band_count: usize,
+    /// Half-width of the zone around each VWAP and band price.
+    pub band: f64,
+    pub evaluation: EvaluationSettings,
+}
+
+/// Anchored VWAP over `bars[anchor..]` using the typical price
+/// `(high + low + close) / 3`. Bars without volume are weighted equally if the
+/// whole span lacks volume.
+pub fn anchored_vwap(bars: &[Bar], anchor: &VwapAnchor) -> Option<AnchoredVwap> {
+    let span = bars.get(anchor.index..).filter(|span| !span.is_empty())?;
+    let typical = |bar: &Bar| (bar.high + bar.low + bar.close) / 3.0;
+    let has_volume = span.iter().any(|bar| bar.volume > 0.0);
+    let weight = |bar: &Bar| {
+        if has_volume {
+            bar.volume.max(0.0)
+        } else {
+            1.0
+        }
+    };
+
+    let total: f64 = span.iter().map(weight).sum();
+    if total <= 0.0 {
+        return None;
+    }
+    let vwap = span
+        .iter()
+        .map(|bar| weight(bar) * typical(bar))
+        .sum::<f64>()
+        / total;
+    let variance = span
+        .iter()
+        .map(|bar| weight(bar) * (typical(bar) - vwap).powi(2))
+        .sum::<f64>()
+        / total;
+
+    Some(AnchoredVwap {
+        label: anchor.label.clone(),
+        vwap,
+        std_dev: variance.sqrt(),
+    })
+}
+
+/// Resolve the built-in anchor families against `bars`.
+///
+/// `ath_time` is the timestamp of the all-time-high bar. Of `swings`, the
+/// `swing_count` largest legs measured in ATR multiples each contribute their
+/// ending swing as an anchor; swings are matched to `bars` by timestamp.
+pub fn builtin_anchors(
+    bars: &[Bar],
+    kinds: &[VwapAnchorKind],
+    ath_time: Option<DateTime<Tz>>,
+    swings: &[SwingPoint],
+    swing_count: usize,
+) -> Vec<VwapAnchor> {
+    let Some(last) = bars.last() else {
+        return Vec::new();
+    };
+    let first_where = |label: &str, keep: &dyn Fn(&Bar) -> bool| {
+        bars.iter().position(keep).map(|index| VwapAnchor {
+            label: label.to_string(),
+            index,
+        })
+    };
+
+    let mut anchors = Vec::new();
+    for kind in kinds {
+        match kind {
+            VwapAnchorKind::Session => anchors.extend(first_where("session", &|bar| {
+                bar.timestamp.date_naive() == last.timestamp.date_naive()
+            })),
+            VwapAnchorKind::Week => anchors.extend(first_where("week", &|bar| {
+                bar.timestamp.iso_week() == last.timestamp.iso_week()
+            })),
+            VwapAnchorKind::Month => anchors.extend(first_where("month", &|bar| {
+                bar.timestamp.year() == last.timestamp.year()
+                    && bar.timestamp.month() == last.timestamp.month()
+            })),
+            VwapAnchorKind::Ath => {
+                anchors.extend(ath_time.and_then(|time| timestamp_anchor(bars, time)).map(
+                    |anchor| VwapAnchor {
+                        label: "ATH".to_string(),
+                        ..anchor
+                    },
+                ))
+            }
+            VwapAnchorKind::Swings => {
+                anchors.extend(major_swing_anchors(bars, swings, swing_count))
+            }
+        }
+    }
+    anchors
+}
+
+/// Anchor at the first bar at or after `timestamp`, if it falls inside `bars`.
+pub fn timestamp_anchor(bars: &[Bar], timestamp: DateTime<Tz>) -> Option<VwapAnchor> {
+    if bars.first()?.timestamp > timestamp {
+        return None;
+    }
+    let index = bars.iter().position(|bar| bar.timestamp >= timestamp)?;
+    Some(VwapAnchor {
+        label: timestamp.format("%Y-%m-%d %H:%M").to_string(),
+        index,
+    })
+}
+
+/// VWAP and band levels for each anchor. The VWAP itself scores 1 and the
+/// `k`σ bands `1 / (1 + k)`, so outer bands rank below the lines they wrap.
+///
+/// A VWAP does not exist before its anchor, so each anchor's levels are
+/// evaluated on `bars[anchor..]` only. `atr` must be aligned with `bars`.
+pub fn anchored_vwap_levels(
+    bars: &[Bar],
+    atr: &[f64],
+    anchors: &[VwapAnchor],
+    settings: &VwapSettings,
+    current_price: f64,
+) -> Vec<Level> {
+    let mut scored = Vec::new();
+    for anchor in anchors {
+        let Some(vwap) = anchored_vwap(bars, anchor) else {
+            continue;
+        };
+        let mut levels = Vec::new();
+        let mut push = |price: f64, suffix: String, confidence: f64| {
+            levels.push(Level::new(
+                price,
+                (price - settings.band, price + settings.band),
+                confidence,
+                LevelSource::Vwap,
+                format!("{}{suffix}", vwap.label),
+                current_price,
+            ));
+        };
+        push(vwap.vwap, String::new(), 1.0);
+        let band_count = if vwap.std_dev > 0.0 {
+            settings.band_count
+        } else {
+            0
+        };
+        for k in 1..=band_count {
+            let offset = k as f64 * vwap.std_dev;
+            let confidence = 1.0 / (1.0 + k as f64);
+            push(vwap.vwap + offset, format!(" +{k}σ"), confidence);
+            push(vwap.vwap - offset, format!(" -{k}σ"), confidence);
+        }
+        scored.extend(evaluate_levels(
+            levels,
+            &bars[anchor.index..],
+            atr.get(anchor.index..).unwrap_or(&[]),
+            &settings.evaluation,
+        ));
+    }
+    scored
+}
+
+fn major_swing_anchors(bars: &[Bar], swings: &[SwingPoint], count: usize) -> Vec<VwapAnchor> {
+    let mut legs = largest_legs(swings, count);
+    legs.sort_by_key(|leg| leg.end.index);
+
+    legs.iter()
+        .map(|leg| &leg.end)
+        .filter_map(|swing| {
+            let kind = match swing.swing_type {
+                SwingType::High => "swing high",
+                SwingType::Low => "swing low",
+            };
+            let anchor = timestamp_anchor(bars, swing.bar.timestamp)?;
+            Some(VwapAnchor {
+                label: format!("{kind} {}", anchor.label),
+                ..anchor
+            })
+        })
+        .collect()
+}
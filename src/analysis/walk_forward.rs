@@ -0,0 +1,159 @@
+use std::ops::Range;
+
+use clap::ValueEnum;
+
+use crate::analysis::sessions::split_sessions;
+use crate::analysis::stats::{evaluate_levels, EvaluationSettings};
+use crate::data::{Bar, Level, LevelType, PerformanceStats};
+
+/// How the training window moves through history.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum WalkForwardWindow {
+    /// Fixed number of sessions ending at day D.
+    Rolling,
+    /// Every session from the start of the data up to day D.
+    Expanding,
+}
+
+impl WalkForwardWindow {
+    pub fn label(&self) -> &'static str {
+        match self {
+            WalkForwardWindow::Rolling => "rolling",
+            WalkForwardWindow::Expanding => "expanding",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct WalkForwardSettings {
+    pub window: WalkForwardWindow,
+    /// Sessions in the (first) training window.
+    pub train_sessions: usize,
+    /// Sessions after the training window the levels are scored on.
+    pub test_sessions: usize,
+    /// Sessions the window advances between folds.
+    pub step_sessions: usize,
+    pub evaluation: EvaluationSettings,
+}
+
+/// Out-of-sample statistics pooled over the level holding one rank in every
+/// fold, e.g. the nearest resistance.
+#[derive(Debug, Clone)]
+pub struct WalkForwardSlot {
+    /// "R1" for the nearest resistance, "S2" for the second support, ...
+    pub name: String,
+    /// Folds that produced a level for this rank.
+    pub levels: usize,
+    pub performance: PerformanceStats,
+}
+
+pub struct WalkForwardResult {
+    pub settings: WalkForwardSettings,
+    /// Folds whose levels were scored, and folds where no levels were built.
+    pub folds: usize,
+    pub skipped: usize,
+    pub slots: Vec<WalkForwardSlot>,
+    pub overall: PerformanceStats,
+}
+
+/// Training and test bar ranges for each fold: train on sessions up to day
+/// D, test on D+1 through D+k, then advance D by the step.
+pub fn walk_forward_folds(
+    bars: &[Bar],
+    settings: &WalkForwardSettings,
+) -> Vec<(Range<usize>, Range<usize>)> {
+    let sessions = split_sessions(bars);
+    let (train, test) = (
+        settings.train_sessions.max(1),
+        settings.test_sessions.max(1),
+    );
+    let mut folds = Vec::new();
+    let mut end = train;
+    while end + test <= sessions.len() {
+        let first = match settings.window {
+            WalkForwardWindow::Rolling => end - train,
+            WalkForwardWindow::Expanding => 0,
+        };
+        folds.push((
+            sessions[first].range.start..sessions[end - 1].range.end,
+            sessions[end].range.start..sessions[end + test - 1].range.end,
+        ));
+        end += settings.step_sessions.max(1);
+    }
+    folds
+}
+
+/// Walk-forward evaluation of the levels produced by `build`.
+///
+/// `build` sees only the training bars of each fold and returns `None` when
+/// it cannot produce levels. The levels are scored on the test bars alone,
+/// against `atr`, which must be aligned with `bars` and causal (see
+/// `compute_causal_atr`), and pooled by their distance rank on each side of
+/// the last training close.
+pub fn walk_forward<F>(
+    bars: &[Bar],
+    atr: &[f64],
+    settings: &WalkForwardSettings,
+    mut build: F,
+) -> WalkForwardResult
+where
+    F: FnMut(&[Bar]) -> Option<Vec<Level>>,
+{
+    let mut result = WalkForwardResult {
+        settings: *settings,
+        folds: 0,
+        skipped: 0,
+        slots: Vec::new(),
+        overall: PerformanceStats::empty(),
+    };
+    for (train, test) in walk_forward_folds(bars, settings) {
+        let Some(levels) = build(&bars[train]).filter(|levels| !levels.is_empty()) else {
+            result.skipped += 1;
+            continue;
+        };
+        result.folds += 1;
+        let test_atr = atr.get(test.clone()).unwrap_or(&[]);
+        let mut scored = evaluate_levels(levels, &bars[test], test_atr, &settings.evaluation);
+        scored.sort_by(|a, b| {
+            a.distance_from_last
+                .partial_cmp(&b.distance_from_last)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+
+        let (mut resistances, mut supports) = (0, 0);
+        for level in &scored {
+            let name = match level.level_type {
+                LevelType::Resistance => {
+                    resistances += 1;
+                    format!("R{resistances}")
+                }
+                LevelType::Support => {
+                    supports += 1;
+                    format!("S{supports}")
+                }
+            };
+            result.overall.merge(&level.performance);
+            match result.slots.iter_mut().find(|slot| slot.name == name) {
+                Some(slot) => {
+                    slot.levels += 1;
+                    slot.performance.merge(&level.performance);
+                }
+                None => result.slots.push(WalkForwardSlot {
+                    name,
+                    levels: 1,
+                    performance: level.performance.clone(),
+                }),
+            }
+        }
+    }
+    // Resistances from the farthest down to R1, then supports outward.
+    result.slots.sort_by_key(|slot| {
+        let rank: i64 = slot.name[1..].parse().unwrap_or(0);
+        if slot.name.starts_with('R') {
+            -rank
+        } else {
+            rank
+        }
+    });
+    result
+}
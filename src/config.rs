@@ -1,5 +1,19 @@
 use clap::{ArgAction, Parser};
 
+use crate::analysis::density::{
+    AdaptiveMode, BandwidthMethod, BoundaryCorrection, KdeEngine, Kernel,
+};
+use crate::analysis::evt::{
+    Declustering, EvtFallback, EvtSample, ExcursionReference, ThresholdSelection,
+};
+use crate::analysis::gpd::{GpdEstimator, ReturnLevelInterval};
+use crate::analysis::pivots::PivotMethod;
+use crate::analysis::projections::VolatilitySource;
+use crate::analysis::sessions::CalendarPeriod;
+use crate::analysis::significance::NullModel;
+use crate::analysis::vwap::VwapAnchorKind;
+use crate::analysis::walk_forward::WalkForwardWindow;
+
 /// Command-line configuration for the quantitative mapping tool.
 #[derive(Debug, Clone, Parser)]
 #[command(author, version, about, long_about = None)]
@@ -52,10 +66,146 @@ pub struct AppConfig {
     #[arg(long, default_value_t = 2)]
     pub ev_max_levels: usize,
 
+    /// Use the configured EVT threshold quantiles or select thresholds automatically.
+    #[arg(long, value_enum, default_value_t = ThresholdSelection::Fixed)]
+    pub evt_threshold_selection: ThresholdSelection,
+
+    /// Enable EVT-based support projection from the lower tail.
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub evt_support: bool,
+
+    /// Lower-tail probability (overall) used for the first EVT support projection.
+    #[arg(long, default_value_t = 0.99)]
+    pub ev_support_tail_probability: f64,
+
+    /// Quantile (0-1) defining the exceedance threshold for EVT supports.
+    #[arg(long, default_value_t = 0.9)]
+    pub ev_support_threshold_quantile: f64,
+
+    /// Maximum number of EVT support levels to generate.
+    #[arg(long, default_value_t = 2)]
+    pub ev_support_max_levels: usize,
+
+    /// Non-EVT projection emitted when an EVT fit produces no level.
+    #[arg(long, value_enum, default_value_t = EvtFallback::None)]
+    pub evt_fallback: EvtFallback,
+
+    /// Session-ATR multiple used by the ATR fallback projection.
+    #[arg(long, default_value_t = 1.0)]
+    pub evt_fallback_atr: f64,
+
+    /// Calendar blocks whose maxima and minima are fitted with a GEV (comma separated).
+    #[arg(long, value_enum, value_delimiter = ',')]
+    pub gev_blocks: Vec<CalendarPeriod>,
+
+    /// Per-block exceedance probabilities projected from the GEV fits (comma separated).
+    #[arg(long, value_delimiter = ',', default_value = "0.01,0.05")]
+    pub gev_probabilities: Vec<f64>,
+
+    /// Minimum number of completed blocks required for a GEV fit.
+    #[arg(long, default_value_t = 10)]
+    pub gev_min_blocks: usize,
+
+    /// Horizons to project expected moves for (comma separated: day, week, month).
+    #[arg(long, value_enum, value_delimiter = ',')]
+    pub expected_move: Vec<CalendarPeriod>,
+
+    /// Sigma multiples of the expected-move levels (comma separated).
+    #[arg(long, value_delimiter = ',', default_value = "1,2")]
+    pub expected_move_sigmas: Vec<f64>,
+
+    /// Volatility estimate behind the expected-move levels.
+    #[arg(long, value_enum, default_value_t = VolatilitySource::Close)]
+    pub expected_move_source: VolatilitySource,
+
+    /// Completed sessions the expected-move volatility is estimated from.
+    #[arg(long, default_value_t = 20)]
+    pub expected_move_sessions: usize,
+
+    /// Skew expected-move levels by the empirical session-return distribution.
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub expected_move_skew: bool,
+
+    /// Estimator for the generalized Pareto tail fit.
+    #[arg(long, value_enum, default_value_t = GpdEstimator::Mle)]
+    pub evt_estimator: GpdEstimator,
+
+    /// Confidence-interval method for EVT return levels.
+    #[arg(long, value_enum, default_value_t = ReturnLevelInterval::Profile)]
+    pub evt_interval: ReturnLevelInterval,
+
+    /// Coverage of EVT return-level confidence intervals.
+    #[arg(long, default_value_t = 0.9)]
+    pub evt_ci_level: f64,
+
+    /// Sample the EVT tail is fitted to: raw bar highs or excursions above a reference.
+    #[arg(long, value_enum, default_value_t = EvtSample::Highs)]
+    pub evt_sample: EvtSample,
+
+    /// Reference price excursions are measured from.
+    #[arg(long, value_enum, default_value_t = ExcursionReference::SessionOpen)]
+    pub evt_reference: ExcursionReference,
+
+    /// Declustering of excursions into independent peaks.
+    #[arg(long, value_enum, default_value_t = Declustering::SessionMaxima)]
+    pub evt_decluster: Declustering,
+
+    /// Largest gap (bars) between exceedances of one cluster in runs declustering.
+    #[arg(long, default_value_t = 30)]
+    pub evt_run_length: usize,
+
+    /// Window (bars) of the rolling-high excursion reference.
+    #[arg(long, default_value_t = 60)]
+    pub evt_rolling_window: usize,
+
     /// KDE grid points for price density estimation.
     #[arg(long, default_value_t = 400)]
     pub kde_points: usize,
 
+    /// Bandwidth selection rule for the price density estimate.
+    #[arg(long, value_enum, default_value_t = BandwidthMethod::Scott)]
+    pub kde_bandwidth: BandwidthMethod,
+
+    /// Bandwidth for `fixed` (price units) or `atr` (ATR multiples) selection.
+    #[arg(long, default_value_t = 1.0)]
+    pub kde_bandwidth_value: f64,
+
+    /// Per-swing adaptive bandwidth mode for the price density estimate.
+    #[arg(long, value_enum, default_value_t = AdaptiveMode::None)]
+    pub kde_adaptive: AdaptiveMode,
+
+    /// Scale each swing's kernel width by the ATR at the swing bar.
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub kde_atr_kernels: bool,
+
+    /// Density evaluation engine; `auto` switches to a fast path on large inputs.
+    #[arg(long, value_enum, default_value_t = KdeEngine::Auto)]
+    pub kde_engine: KdeEngine,
+
+    /// Smoothing kernel for the price density estimate.
+    #[arg(long, value_enum, default_value_t = Kernel::Gaussian)]
+    pub kde_kernel: Kernel,
+
+    /// Boundary correction at the lowest and highest swing prices.
+    #[arg(long, value_enum, default_value_t = BoundaryCorrection::None)]
+    pub kde_boundary: BoundaryCorrection,
+
+    /// Minimum peak prominence as a fraction of the maximum density.
+    #[arg(long, default_value_t = 0.02)]
+    pub peak_min_prominence: f64,
+
+    /// Minimum peak height as a fraction of the maximum density.
+    #[arg(long, default_value_t = 0.05)]
+    pub peak_min_height: f64,
+
+    /// Minimum separation between density peaks in ATR multiples.
+    #[arg(long, default_value_t = 0.5)]
+    pub peak_min_separation_atr: f64,
+
+    /// Fraction of the peak density that bounds each level's zone.
+    #[arg(long, default_value_t = 0.5)]
+    pub zone_fraction: f64,
+
     /// DBSCAN epsilon scaling factor (applied to auto-epsilon outcome).
     #[arg(long, default_value_t = 1.0)]
     pub dbscan_eps_factor: f64,
@@ -64,7 +214,7 @@ pub struct AppConfig {
     #[arg(long, default_value_t = 3)]
     pub dbscan_min_points: usize,
 
-    /// Confidence band width in ATR multiples.
+    /// Half-width in ATR multiples for zones not derived from the density curve.
     #[arg(long, default_value_t = 1.0)]
     pub confidence_band_atr: f64,
 
@@ -72,6 +222,124 @@ pub struct AppConfig {
     #[arg(long, default_value_t = 12)]
     pub max_levels: usize,
 
+    /// Add volume-at-price levels (POC, value area, HVN/LVN).
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub volume_profile: bool,
+
+    /// Instrument tick size used for price binning.
+    #[arg(long, default_value_t = 0.25)]
+    pub tick_size: f64,
+
+    /// Share of volume enclosed by the value area.
+    #[arg(long, default_value_t = 0.7)]
+    pub value_area: f64,
+
+    /// Number of most recent sessions that get their own volume profile levels.
+    #[arg(long, default_value_t = 1)]
+    pub profile_sessions: usize,
+
+    /// Smoothing window (ticks) applied before locating HVN/LVN.
+    #[arg(long, default_value_t = 9)]
+    pub profile_smoothing_ticks: usize,
+
+    /// Add market-profile (TPO) levels: POC, value area, IB, single prints,
+    /// poor highs/lows and excess tails.
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub market_profile: bool,
+
+    /// Height of one TPO row in ticks.
+    #[arg(long, default_value_t = 4)]
+    pub tpo_row_ticks: usize,
+
+    /// Length of one TPO letter period in minutes.
+    #[arg(long, default_value_t = 30)]
+    pub tpo_period_minutes: i64,
+
+    /// Number of most recent sessions to build TPO profiles for.
+    #[arg(long, default_value_t = 1)]
+    pub tpo_sessions: usize,
+
+    /// Print the letter profile of each TPO session.
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub print_tpo: bool,
+
+    /// Anchor families for anchored VWAP levels (comma separated).
+    #[arg(long, value_enum, value_delimiter = ',')]
+    pub vwap_anchors: Vec<VwapAnchorKind>,
+
+    /// Additional VWAP anchor timestamps in Eastern time ("YYYY-MM-DD HH:MM").
+    #[arg(long = "vwap-anchor-time")]
+    pub vwap_anchor_times: Vec<String>,
+
+    /// Number of largest swing legs used as VWAP anchors.
+    #[arg(long, default_value_t = 2)]
+    pub vwap_swings: usize,
+
+    /// Number of ±σ band pairs around each anchored VWAP.
+    #[arg(long, default_value_t = 3)]
+    pub vwap_bands: usize,
+
+    /// Add session reference levels: prior day/week/month OHLC, overnight
+    /// high/low, opening range, initial balance and gap fill.
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub reference_levels: bool,
+
+    /// Length of the opening range in minutes.
+    #[arg(long, default_value_t = 15)]
+    pub opening_range_minutes: i64,
+
+    /// Length of the initial balance in minutes.
+    #[arg(long, default_value_t = 60)]
+    pub ib_minutes: i64,
+
+    /// Fold reference levels into KDE levels within the merge tolerance.
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub merge_reference: bool,
+
+    /// Merge tolerance for reference levels in ATR multiples.
+    #[arg(long, default_value_t = 0.25)]
+    pub reference_merge_atr: f64,
+
+    /// Pivot point methods to compute (comma separated).
+    #[arg(long, value_enum, value_delimiter = ',')]
+    pub pivots: Vec<PivotMethod>,
+
+    /// Periods the pivots are computed on (comma separated).
+    #[arg(long, value_enum, value_delimiter = ',', default_value = "day")]
+    pub pivot_periods: Vec<CalendarPeriod>,
+
+    /// Number of largest swing legs to project Fibonacci levels from (0 disables).
+    #[arg(long, default_value_t = 0)]
+    pub fib_legs: usize,
+
+    /// Clustering tolerance for Fibonacci confluence in ATR multiples.
+    #[arg(long, default_value_t = 0.25)]
+    pub fib_confluence_atr: f64,
+
+    /// Minimum number of distinct legs behind an emitted Fibonacci level.
+    #[arg(long, default_value_t = 1)]
+    pub fib_min_confluence: usize,
+
+    /// Add round-number levels with a significance test against shifted grids.
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub round_numbers: bool,
+
+    /// Round-number increments (comma separated); chosen from the price if empty.
+    #[arg(long, value_delimiter = ',')]
+    pub round_increments: Vec<f64>,
+
+    /// Number of round numbers nearest the current price to report.
+    #[arg(long, default_value_t = 8)]
+    pub round_max_levels: usize,
+
+    /// Number of shifted grids used as the null for round-number tests.
+    #[arg(long, default_value_t = 40)]
+    pub round_null_offsets: usize,
+
+    /// Significance level for marking round numbers.
+    #[arg(long, default_value_t = 0.1)]
+    pub round_alpha: f64,
+
     /// Lookahead bars for reaction evaluation.
     #[arg(long, default_value_t = 20)]
     pub reaction_lookahead: usize,
@@ -79,4 +347,77 @@ pub struct AppConfig {
     /// Reaction move threshold in ATR multiples.
     #[arg(long, default_value_t = 0.5)]
     pub reaction_move_atr: f64,
+
+    /// Distance beyond a level's zone, in ATR multiples, that price must
+    /// clear before a re-entry counts as a new touch.
+    #[arg(long, default_value_t = 0.25)]
+    pub touch_reset_atr: f64,
+
+    /// Close beyond a level, in ATR multiples, that classifies a touch as a
+    /// break.
+    #[arg(long, default_value_t = 0.5)]
+    pub breakout_atr: f64,
+
+    /// Stop for the per-touch trade simulation, in ATR multiples beyond the
+    /// far edge of the level's zone.
+    #[arg(long, default_value_t = 0.5)]
+    pub trade_stop_atr: f64,
+
+    /// Target for the per-touch trade simulation, in ATR multiples from entry.
+    #[arg(long, default_value_t = 1.0)]
+    pub trade_target_atr: f64,
+
+    /// Bars after entry before a simulated trade is closed at market.
+    #[arg(long, default_value_t = 30)]
+    pub trade_time_stop: usize,
+
+    /// Slippage in price points on simulated entries and market exits.
+    #[arg(long, default_value_t = 0.0)]
+    pub trade_slippage: f64,
+
+    /// Round-trip commission in price points per simulated trade.
+    #[arg(long, default_value_t = 0.0)]
+    pub trade_commission: f64,
+
+    /// Score KDE levels out of sample: rebuild them on each training window
+    /// and evaluate only on the sessions that follow it.
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub walk_forward: bool,
+
+    /// Walk-forward training windows to compare (comma separated).
+    #[arg(long, value_enum, value_delimiter = ',', default_value = "rolling")]
+    pub walk_forward_window: Vec<WalkForwardWindow>,
+
+    /// Walk-forward training lengths in sessions to compare (comma separated).
+    #[arg(long, value_delimiter = ',', default_value = "20")]
+    pub walk_forward_train: Vec<usize>,
+
+    /// Sessions after each training window that its levels are scored on.
+    #[arg(long, default_value_t = 1)]
+    pub walk_forward_test: usize,
+
+    /// Sessions the walk-forward window advances between folds.
+    #[arg(long, default_value_t = 1)]
+    pub walk_forward_step: usize,
+
+    /// Test every reported level's hit rate and trade expectancy against a
+    /// randomised baseline.
+    #[arg(long, value_enum)]
+    pub significance: Option<NullModel>,
+
+    /// Null draws per level (random levels) or surrogate paths.
+    #[arg(long, default_value_t = 100)]
+    pub significance_draws: usize,
+
+    /// Bars per block for the block-bootstrap null.
+    #[arg(long, default_value_t = 60)]
+    pub significance_block: usize,
+
+    /// False discovery rate for the Benjamini-Hochberg significance flags.
+    #[arg(long, default_value_t = 0.1)]
+    pub significance_fdr: f64,
+
+    /// Seed for the null-model draws.
+    #[arg(long, default_value_t = 1)]
+    pub significance_seed: u64,
 }
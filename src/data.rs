@@ -50,14 +50,232 @@ pub enum LevelType {
     Resistance,
 }
 
+/// Engine that produced a level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum LevelSource {
+    /// Peak of the swing-price density.
+    Density,
+    /// Peaks-over-threshold tail projection.
+    Evt,
+    /// Volume-at-price profile node (POC, value area edge, HVN, LVN).
+    VolumeProfile,
+    /// Time-price-opportunity profile reference (POC, value area, IB, single
+    /// prints, poor extremes, excess tails).
+    MarketProfile,
+    /// Anchored VWAP or one of its standard-deviation bands.
+    Vwap,
+    /// Mechanical session reference (prior period OHLC, overnight range,
+    /// opening range, initial balance, gap fill).
+    Reference,
+    /// Floor-trader pivot point.
+    Pivot,
+    /// Fibonacci retracement/extension cluster from the largest swing legs.
+    Fibonacci,
+    /// Psychological round number.
+    RoundNumber,
+    /// Volatility projection (ATR multiple or expected move); not a fitted
+    /// tail model.
+    Projection,
+}
+
+impl LevelSource {
+    pub fn label(&self) -> &'static str {
+        match self {
+            LevelSource::Density => "KDE",
+            LevelSource::Evt => "EVT",
+            LevelSource::VolumeProfile => "VP",
+            LevelSource::MarketProfile => "TPO",
+            LevelSource::Vwap => "VWAP",
+            LevelSource::Reference => "REF",
+            LevelSource::Pivot => "PIVOT",
+            LevelSource::Fibonacci => "FIB",
+            LevelSource::RoundNumber => "ROUND",
+            LevelSource::Projection => "PROJ",
+        }
+    }
+}
+
+/// How price resolved a touch, relative to the side it approached from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum TouchOutcome {
+    /// Turned back to the approach side by the reaction distance.
+    Bounce,
+    /// Closed through the level by the breakout distance and stayed there.
+    Breakout,
+    /// Closed through the level, then closed back on the approach side.
+    FalseBreakout,
+    /// Neither bounced nor broke within the lookahead.
+    Chop,
+}
+
+/// Touch outcomes from one approach side.
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct OutcomeCounts {
+    pub bounce: usize,
+    pub breakout: usize,
+    pub false_breakout: usize,
+    pub chop: usize,
+    /// Touches whose best move back to the approach side, beyond the near
+    /// zone edge, reached the reaction distance, and the sum of those moves.
+    /// Unlike `PerformanceStats::hit_rate` this ignores the level's support
+    /// or resistance role, so it agrees with the bounce classification.
+    pub hits: usize,
+    pub total_reaction: f64,
+}
+
+impl OutcomeCounts {
+    pub fn record(&mut self, outcome: TouchOutcome) {
+        match outcome {
+            TouchOutcome::Bounce => self.bounce += 1,
+            TouchOutcome::Breakout => self.breakout += 1,
+            TouchOutcome::FalseBreakout => self.false_breakout += 1,
+            TouchOutcome::Chop => self.chop += 1,
+        }
+    }
+
+    pub fn record_reaction(&mut self, reaction: f64, hit: bool) {
+        self.total_reaction += reaction;
+        if hit {
+            self.hits += 1;
+        }
+    }
+
+    pub fn total(&self) -> usize {
+        self.bounce + self.breakout + self.false_breakout + self.chop
+    }
+
+    pub fn hit_rate(&self) -> f64 {
+        match self.total() {
+            0 => 0.0,
+            total => self.hits as f64 / total as f64,
+        }
+    }
+
+    pub fn avg_reaction(&self) -> f64 {
+        match self.total() {
+            0 => 0.0,
+            total => self.total_reaction / total as f64,
+        }
+    }
+
+    pub fn count(&self, outcome: TouchOutcome) -> usize {
+        match outcome {
+            TouchOutcome::Bounce => self.bounce,
+            TouchOutcome::Breakout => self.breakout,
+            TouchOutcome::FalseBreakout => self.false_breakout,
+            TouchOutcome::Chop => self.chop,
+        }
+    }
+
+    /// Share of classified touches with `outcome`; 0 when there are none.
+    pub fn probability(&self, outcome: TouchOutcome) -> f64 {
+        match self.total() {
+            0 => 0.0,
+            total => self.count(outcome) as f64 / total as f64,
+        }
+    }
+
+    fn add(&mut self, other: &OutcomeCounts) {
+        self.bounce += other.bounce;
+        self.breakout += other.breakout;
+        self.false_breakout += other.false_breakout;
+        self.chop += other.chop;
+        self.hits += other.hits;
+        self.total_reaction += other.total_reaction;
+    }
+}
+
+/// Results of the simulated trades taken at each touch, in price points and
+/// in multiples of initial risk (R).
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct TradeStats {
+    pub trades: usize,
+    pub wins: usize,
+    pub total_r: f64,
+    pub gross_profit: f64,
+    pub gross_loss: f64,
+}
+
+impl TradeStats {
+    pub fn record(&mut self, pnl: f64, r: f64) {
+        self.trades += 1;
+        self.total_r += r;
+        if pnl > 0.0 {
+            self.wins += 1;
+            self.gross_profit += pnl;
+        } else {
+            self.gross_loss -= pnl;
+        }
+    }
+
+    pub fn win_rate(&self) -> f64 {
+        match self.trades {
+            0 => 0.0,
+            trades => self.wins as f64 / trades as f64,
+        }
+    }
+
+    pub fn avg_r(&self) -> f64 {
+        match self.trades {
+            0 => 0.0,
+            trades => self.total_r / trades as f64,
+        }
+    }
+
+    /// Mean net result per trade in price points.
+    pub fn expectancy(&self) -> f64 {
+        match self.trades {
+            0 => 0.0,
+            trades => (self.gross_profit - self.gross_loss) / trades as f64,
+        }
+    }
+
+    /// Gross profit over gross loss; `None` without losing trades.
+    pub fn profit_factor(&self) -> Option<f64> {
+        (self.gross_loss > 0.0).then(|| self.gross_profit / self.gross_loss)
+    }
+
+    fn add(&mut self, other: &TradeStats) {
+        self.trades += other.trades;
+        self.wins += other.wins;
+        self.total_r += other.total_r;
+        self.gross_profit += other.gross_profit;
+        self.gross_loss += other.gross_loss;
+    }
+}
+
 #[derive(Debug, Clone, Serialize)]
 pub struct PerformanceStats {
+    /// Distinct touch episodes: entries into the zone from outside.
     pub touches: usize,
+    /// Touches with at least one bar after entry to measure a reaction on.
     pub tests: usize,
     pub hit_rate: f64,
     pub avg_reaction: f64,
     pub max_favorable_excursion: f64,
     pub avg_reaction_bars: f64,
+    /// Bars overlapping the zone across all touch episodes.
+    pub bars_in_zone: usize,
+    /// Touches entered from above and from below the zone.
+    pub from_above: usize,
+    pub from_below: usize,
+    /// First touch of each scored span, counted separately since later
+    /// touches of an already-tested level tend to react less.
+    pub first_tests: usize,
+    pub first_hits: usize,
+    pub first_avg_reaction: f64,
+    /// Outcomes of tested touches entered from above and from below.
+    pub outcomes_above: OutcomeCounts,
+    pub outcomes_below: OutcomeCounts,
+    /// Mean and worst move against the approach side from the zone edge
+    /// within the lookahead, per test.
+    pub avg_adverse_excursion: f64,
+    pub max_adverse_excursion: f64,
+    /// Tests that reached the simulated stop within the lookahead, and the
+    /// mean bars from entry until they did.
+    pub adverse_hits: usize,
+    pub avg_bars_to_adverse: f64,
+    pub trades: TradeStats,
 }
 
 impl PerformanceStats {
@@ -69,19 +287,156 @@ impl PerformanceStats {
             avg_reaction: 0.0,
             max_favorable_excursion: 0.0,
             avg_reaction_bars: 0.0,
+            bars_in_zone: 0,
+            from_above: 0,
+            from_below: 0,
+            first_tests: 0,
+            first_hits: 0,
+            first_avg_reaction: 0.0,
+            outcomes_above: OutcomeCounts::default(),
+            outcomes_below: OutcomeCounts::default(),
+            avg_adverse_excursion: 0.0,
+            max_adverse_excursion: 0.0,
+            adverse_hits: 0,
+            avg_bars_to_adverse: 0.0,
+            trades: TradeStats::default(),
+        }
+    }
+
+    /// Touch outcomes from both approach sides.
+    pub fn outcomes(&self) -> OutcomeCounts {
+        let mut outcomes = self.outcomes_above;
+        outcomes.add(&self.outcomes_below);
+        outcomes
+    }
+
+    pub fn first_hit_rate(&self) -> f64 {
+        if self.first_tests == 0 {
+            0.0
+        } else {
+            self.first_hits as f64 / self.first_tests as f64
+        }
+    }
+
+    /// Pool another set of statistics into this one, weighting averages by
+    /// the number of tests on each side.
+    pub fn merge(&mut self, other: &PerformanceStats) {
+        let first_tests = self.first_tests + other.first_tests;
+        if first_tests > 0 {
+            self.first_avg_reaction = (self.first_avg_reaction * self.first_tests as f64
+                + other.first_avg_reaction * other.first_tests as f64)
+                / first_tests as f64;
         }
+        self.first_tests = first_tests;
+        self.first_hits += other.first_hits;
+        self.bars_in_zone += other.bars_in_zone;
+        self.from_above += other.from_above;
+        self.from_below += other.from_below;
+        self.outcomes_above.add(&other.outcomes_above);
+        self.outcomes_below.add(&other.outcomes_below);
+        self.touches += other.touches;
+        self.trades.add(&other.trades);
+        let adverse_hits = self.adverse_hits + other.adverse_hits;
+        if adverse_hits > 0 {
+            self.avg_bars_to_adverse = (self.avg_bars_to_adverse * self.adverse_hits as f64
+                + other.avg_bars_to_adverse * other.adverse_hits as f64)
+                / adverse_hits as f64;
+        }
+        self.adverse_hits = adverse_hits;
+
+        let tests = self.tests + other.tests;
+        if tests == 0 {
+            return;
+        }
+        let (own, theirs) = (self.tests as f64, other.tests as f64);
+        let pool = |a: f64, b: f64| (a * own + b * theirs) / tests as f64;
+        self.hit_rate = pool(self.hit_rate, other.hit_rate);
+        self.avg_reaction = pool(self.avg_reaction, other.avg_reaction);
+        self.avg_reaction_bars = pool(self.avg_reaction_bars, other.avg_reaction_bars);
+        self.avg_adverse_excursion = pool(self.avg_adverse_excursion, other.avg_adverse_excursion);
+        self.max_adverse_excursion = self.max_adverse_excursion.max(other.max_adverse_excursion);
+        self.max_favorable_excursion = self
+            .max_favorable_excursion
+            .max(other.max_favorable_excursion);
+        self.tests = tests;
     }
 }
 
+/// Level performance measured against a randomised null model.
+#[derive(Debug, Clone, Serialize)]
+pub struct Significance {
+    /// Observed hit rate and trade expectancy on the tested bars.
+    pub hit_rate: f64,
+    pub expectancy: f64,
+    /// Mean of the same statistics over the null draws.
+    pub null_hit_rate: f64,
+    pub null_expectancy: f64,
+    /// One-sided empirical p-values of the observed statistics.
+    pub hit_rate_p: f64,
+    pub expectancy_p: f64,
+    /// Null draws with at least one test.
+    pub draws: usize,
+    /// Benjamini-Hochberg discoveries across all tested levels.
+    pub hit_rate_significant: bool,
+    pub expectancy_significant: bool,
+}
+
 #[derive(Debug, Clone, Serialize)]
 pub struct Level {
     pub price: f64,
     pub density: f64,
+    /// Width of the density peak at half prominence (0 for non-KDE levels).
+    pub peak_width: f64,
     pub confidence: f64,
-    pub confidence_band: f64,
+    /// Lower and upper bound of the price zone around `price`; not
+    /// necessarily symmetric.
+    pub zone_low: f64,
+    pub zone_high: f64,
     pub level_type: LevelType,
+    pub source: LevelSource,
+    /// Short description shown next to the source, e.g. "POC 2025-09-19".
+    pub label: String,
     pub performance: PerformanceStats,
     pub distance_from_last: f64,
+    /// Result of the null-model test, when one was run.
+    pub significance: Option<Significance>,
+}
+
+impl Level {
+    /// Level that does not come from the density curve, typed as support or
+    /// resistance relative to `current_price`.
+    pub fn new(
+        price: f64,
+        zone: (f64, f64),
+        confidence: f64,
+        source: LevelSource,
+        label: String,
+        current_price: f64,
+    ) -> Self {
+        Self {
+            price,
+            density: 0.0,
+            peak_width: 0.0,
+            confidence,
+            zone_low: zone.0.min(price),
+            zone_high: zone.1.max(price),
+            level_type: if price >= current_price {
+                LevelType::Resistance
+            } else {
+                LevelType::Support
+            },
+            source,
+            label,
+            performance: PerformanceStats::empty(),
+            distance_from_last: (price - current_price).abs(),
+            significance: None,
+        }
+    }
+
+    /// Whether a bar spanning `low..=high` trades into the zone.
+    pub fn overlaps(&self, low: f64, high: f64) -> bool {
+        low <= self.zone_high && high >= self.zone_low
+    }
 }
 
 /// Utility describing the regular trading hours window in Eastern time.
@@ -2,7 +2,7 @@ use std::fs::File;
 use std::path::Path;
 
 use anyhow::{anyhow, Context, Result};
-use chrono::{NaiveDate, NaiveDateTime, NaiveTime, TimeZone};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, TimeZone};
 use chrono_tz::{America::New_York, Tz};
 use csv::StringRecord;
 use thiserror::Error;
@@ -78,12 +78,7 @@ fn parse_record(record: &StringRecord) -> Result<Option<Bar>> {
             .ok_or_else(|| anyhow!(LoaderError::Timestamp(record.clone())))?
     };
 
-    let tz: Tz = New_York;
-    let timestamp = match tz.from_local_datetime(&datetime) {
-        chrono::LocalResult::Single(dt) => dt,
-        chrono::LocalResult::Ambiguous(dt, _) => dt,
-        chrono::LocalResult::None => tz.from_utc_datetime(&datetime),
-    };
+    let timestamp = to_eastern(&datetime);
 
     let open = parse_number(fields.get(offset).map(String::as_str), "open")?;
     let high = parse_number(fields.get(offset + 1).map(String::as_str), "high")?;
@@ -101,6 +96,26 @@ fn parse_record(record: &StringRecord) -> Result<Option<Bar>> {
     }))
 }
 
+/// Parse a user-supplied Eastern-time timestamp (`YYYY-MM-DD HH:MM[:SS]` or
+/// a bare date, taken as midnight).
+pub fn parse_eastern_timestamp(value: &str) -> Result<DateTime<Tz>> {
+    let trimmed = value.trim();
+    let datetime = match trimmed.split_once([' ', 'T']) {
+        Some((date, time)) => parse_datetime_pair(date, time.trim())?,
+        None => NaiveDateTime::new(parse_date(trimmed)?, NaiveTime::MIN),
+    };
+    Ok(to_eastern(&datetime))
+}
+
+fn to_eastern(datetime: &NaiveDateTime) -> DateTime<Tz> {
+    let tz: Tz = New_York;
+    match tz.from_local_datetime(datetime) {
+        chrono::LocalResult::Single(dt) => dt,
+        chrono::LocalResult::Ambiguous(dt, _) => dt,
+        chrono::LocalResult::None => tz.from_utc_datetime(datetime),
+    }
+}
+
 fn parse_number(value: Option<&str>, field: &'static str) -> Result<f64> {
     let value = value.ok_or_else(|| LoaderError::ParseNumber {
         field,
@@ -177,6 +192,7 @@ fn parse_time(value: &str) -> Result<NaiveTime> {
     Err(LoaderError::Timestamp(StringRecord::from(vec![value.to_string()])).into())
 }
 
+#[allow(clippy::iter_overeager_cloned)]
 pub fn filter_rth(bars: &[Bar], rth: RthWindow) -> Vec<Bar> {
     bars.iter()
         .cloned()
@@ -7,9 +7,18 @@ mod output;
 use std::path::Path;
 
 use analysis::{
-    auto_dbscan_epsilon, build_levels, cluster_swings, compute_atr, compute_density_curve,
-    compute_evt_resistances, detect_peaks, detect_swings, evaluate_levels, ClusterResult,
-    DensityAnalysis,
+    anchored_vwap_levels, atr_projection, auto_dbscan_epsilon, auto_increments, build_levels,
+    build_tpo_profiles, builtin_anchors, cluster_swings, compute_atr, compute_causal_atr,
+    compute_density_curve, compute_evt_resistances, compute_evt_supports, compute_fibonacci_levels,
+    compute_gev_levels, compute_pivot_levels, compute_reference_levels,
+    compute_round_number_levels, compute_volume_profile_levels, detect_peaks, detect_swings,
+    evaluate_levels, expected_move_levels, market_profile_levels, merge_reference_levels,
+    session_true_range, session_volatility, test_significance, timestamp_anchor,
+    vol_cone_projection, walk_forward, ClusterResult, DensityAnalysis, DensitySettings,
+    EvaluationSettings, EvtFallback, EvtSettings, ExpectedMoveSettings, FibonacciSettings,
+    GevSettings, KdeEngine, MarketProfileSettings, PeakSettings, PivotSettings, ReferenceSettings,
+    RoundNumberSettings, SignificanceSettings, TailSide, TradeSettings, VolumeProfileSettings,
+    VwapSettings, WalkForwardSettings,
 };
 use anyhow::{bail, Context, Result};
 use chrono::{DateTime, Duration, TimeZone};
@@ -17,13 +26,20 @@ use chrono_tz::{America::New_York, Tz};
 use clap::Parser;
 
 use config::AppConfig;
-use data::{Bar, Level, PerformanceStats, RthWindow, SwingPoint};
-use loader::{filter_rth, load_bars_from_csv, validate_series};
-use output::{print_report, AthContext};
+use data::{Bar, Level, LevelSource, PerformanceStats, RthWindow, SwingPoint};
+use loader::{filter_rth, load_bars_from_csv, parse_eastern_timestamp, validate_series};
+use output::{
+    print_evt_fit, print_evt_thresholds, print_gev_fits, print_pivot_comparison, print_report,
+    print_round_number_tests, print_significance, print_touch_outcomes, print_tpo_profile,
+    print_trade_stats, print_walk_forward, AthContext,
+};
 
 #[derive(Clone, Copy)]
 struct AnalysisSettings {
     recency_half_life_days: Option<f64>,
+    evaluation: EvaluationSettings,
+    /// Print swing and cluster diagnostics; off for walk-forward refits.
+    verbose: bool,
 }
 
 struct AnalysisResult {
@@ -31,6 +47,7 @@ struct AnalysisResult {
     mean_atr: f64,
     density: DensityAnalysis,
     levels: Vec<Level>,
+    swings: Vec<SwingPoint>,
     swing_count: usize,
 }
 
@@ -56,6 +73,19 @@ fn run(config: &AppConfig) -> Result<()> {
     }
     validate_series(&bars)?;
 
+    let evaluation = EvaluationSettings {
+        reaction_lookahead: config.reaction_lookahead,
+        reaction_move_atr: config.reaction_move_atr,
+        reset_atr: config.touch_reset_atr,
+        breakout_atr: config.breakout_atr,
+        trade: TradeSettings {
+            stop_atr: config.trade_stop_atr,
+            target_atr: config.trade_target_atr,
+            time_stop: config.trade_time_stop,
+            slippage: config.trade_slippage,
+            commission: config.trade_commission,
+        },
+    };
     let base_half_life = if config.strong_recency { 15.0 } else { 30.0 };
     let target_swings = config.dbscan_min_points.max(8);
 
@@ -91,6 +121,8 @@ fn run(config: &AppConfig) -> Result<()> {
             config,
             AnalysisSettings {
                 recency_half_life_days: Some(base_half_life),
+                evaluation,
+                verbose: true,
             },
         )?;
 
@@ -112,18 +144,22 @@ fn run(config: &AppConfig) -> Result<()> {
             config,
             AnalysisSettings {
                 recency_half_life_days: Some(base_half_life),
+                evaluation,
+                verbose: true,
             },
         )
         .expect("analysis failed")
     });
 
-    if config.regime_aware {
+    let (mut final_levels, current_price, ath, eval_bars, eval_atr) = if config.regime_aware {
         println!("Running regime-aware aggregation (recent vs. full history)...");
         let historical_result = run_single_analysis(
             &bars,
             config,
             AnalysisSettings {
                 recency_half_life_days: Some(base_half_life * 2.0),
+                evaluation,
+                verbose: true,
             },
         )?;
 
@@ -151,111 +187,442 @@ fn run(config: &AppConfig) -> Result<()> {
             combined_levels.truncate(max_slots);
         }
 
-        let evaluated_levels = evaluate_levels(
-            combined_levels,
-            &bars,
-            &historical_result.atr,
-            config.reaction_lookahead,
-            config.reaction_move_atr,
-        );
+        let evaluated_levels =
+            evaluate_levels(combined_levels, &bars, &historical_result.atr, &evaluation);
 
-        let mut final_levels = evaluated_levels;
-        if config.evt_resistance {
-            let evt_source = if config.ev_lookback_days > 0 {
-                filter_by_lookback(&analysis_bars, config.ev_lookback_days)
-            } else {
-                analysis_bars.clone()
-            };
-            let tail_probs = build_evt_tail_probs(config.ev_tail_probability, config.ev_max_levels);
-            if !tail_probs.is_empty() {
-                let mut base_band = recent_result.mean_atr * config.confidence_band_atr;
-                if !base_band.is_finite() || base_band <= 0.0 {
-                    base_band = (current_price.abs() * 0.001).max(1.0);
-                }
-                let evt_levels = compute_evt_resistances(
-                    &evt_source,
-                    &tail_probs,
-                    config.ev_threshold_quantile,
-                    base_band,
-                    current_price,
-                );
-                if !evt_levels.is_empty() {
-                    println!(
-                        "EVT projected resistances: {}",
-                        evt_levels
-                            .iter()
-                            .map(|lvl| format!("{:.2}", lvl.price))
-                            .collect::<Vec<_>>()
-                            .join(", ")
-                    );
-                    final_levels.extend(evt_levels);
-                }
-            }
-        }
-
-        final_levels.sort_by(|a, b| {
-            b.confidence
-                .partial_cmp(&a.confidence)
-                .unwrap_or(std::cmp::Ordering::Equal)
-        });
-        if final_levels.len() > max_slots {
-            final_levels.truncate(max_slots);
-        }
-
-        let ath = compute_ath(&bars);
-        print_report(&final_levels, current_price, ath, &recent_result.density);
+        (
+            evaluated_levels,
+            current_price,
+            compute_ath(&bars),
+            bars.as_slice(),
+            historical_result.atr,
+        )
     } else {
         let current_price = analysis_bars
             .last()
             .map(|bar| bar.close)
             .unwrap_or_default();
-        let ath = compute_ath(&analysis_bars);
-
-        let mut final_levels = recent_result.levels.clone();
-        if config.evt_resistance {
-            let evt_source = if config.ev_lookback_days > 0 {
-                filter_by_lookback(&analysis_bars, config.ev_lookback_days)
-            } else {
-                analysis_bars.clone()
+        (
+            recent_result.levels.clone(),
+            current_price,
+            compute_ath(&analysis_bars),
+            analysis_bars.as_slice(),
+            recent_result.atr.clone(),
+        )
+    };
+
+    let evt_tails = [
+        (
+            TailSide::Upper,
+            config.evt_resistance,
+            config.ev_tail_probability,
+            config.ev_threshold_quantile,
+            config.ev_max_levels,
+        ),
+        (
+            TailSide::Lower,
+            config.evt_support,
+            config.ev_support_tail_probability,
+            config.ev_support_threshold_quantile,
+            config.ev_support_max_levels,
+        ),
+    ];
+    let mut evt_slots = 0;
+    let mut evt_fallbacks = Vec::new();
+    for (side, enabled, tail_probability, threshold_quantile, max_levels) in evt_tails {
+        if !enabled {
+            continue;
+        }
+        evt_slots += max_levels;
+        let evt_source = if config.ev_lookback_days > 0 {
+            filter_by_lookback(&analysis_bars, config.ev_lookback_days)
+        } else {
+            analysis_bars.clone()
+        };
+        let tail_probs = build_evt_tail_probs(tail_probability, max_levels);
+        if !tail_probs.is_empty() {
+            let mut base_band = recent_result.mean_atr * config.confidence_band_atr;
+            if !base_band.is_finite() || base_band <= 0.0 {
+                base_band = (current_price.abs() * 0.001).max(1.0);
+            }
+            let settings = EvtSettings {
+                threshold_quantile,
+                threshold_selection: config.evt_threshold_selection,
+                estimator: config.evt_estimator,
+                interval: config.evt_interval,
+                ci_level: config.evt_ci_level,
+                band: base_band,
+                sample: config.evt_sample,
+                reference: config.evt_reference,
+                declustering: config.evt_decluster,
+                run_length: config.evt_run_length,
+                rolling_window: config.evt_rolling_window,
             };
-            let tail_probs = build_evt_tail_probs(config.ev_tail_probability, config.ev_max_levels);
-            if !tail_probs.is_empty() {
-                let mut base_band = recent_result.mean_atr * config.confidence_band_atr;
-                if !base_band.is_finite() || base_band <= 0.0 {
-                    base_band = (current_price.abs() * 0.001).max(1.0);
+            let evt = match side {
+                TailSide::Upper => {
+                    compute_evt_resistances(&evt_source, &tail_probs, &settings, current_price)
                 }
-                let evt_levels = compute_evt_resistances(
-                    &evt_source,
-                    &tail_probs,
-                    config.ev_threshold_quantile,
-                    base_band,
-                    current_price,
-                );
-                if !evt_levels.is_empty() {
+                TailSide::Lower => {
+                    compute_evt_supports(&evt_source, &tail_probs, &settings, current_price)
+                }
+            };
+            if !evt.thresholds.is_empty() {
+                print_evt_thresholds(&evt.thresholds, side);
+            }
+            if let Some(summary) = &evt.fit {
+                print_evt_fit(summary, config.evt_interval, config.evt_ci_level);
+            }
+            if let Some(failure) = &evt.failure {
+                println!("EVT {} fit failed: {failure}", side.label());
+                let fallbacks: Vec<Level> = match config.evt_fallback {
+                    EvtFallback::None => Vec::new(),
+                    EvtFallback::Atr => session_true_range(&evt_source)
+                        .map(|range| {
+                            atr_projection(
+                                current_price,
+                                range,
+                                config.evt_fallback_atr,
+                                side,
+                                base_band,
+                            )
+                        })
+                        .into_iter()
+                        .collect(),
+                    EvtFallback::VolCone => session_volatility(&evt_source)
+                        .map(|volatility| {
+                            tail_probs
+                                .iter()
+                                .filter_map(|&prob| {
+                                    vol_cone_projection(
+                                        current_price,
+                                        volatility,
+                                        prob,
+                                        side,
+                                        base_band,
+                                    )
+                                })
+                                .collect()
+                        })
+                        .unwrap_or_default(),
+                };
+                if !fallbacks.is_empty() {
                     println!(
-                        "EVT projected resistances: {}",
-                        evt_levels
+                        "Non-EVT {} fallback ({}): {}",
+                        side.label(),
+                        config.evt_fallback.label(),
+                        fallbacks
                             .iter()
                             .map(|lvl| format!("{:.2}", lvl.price))
                             .collect::<Vec<_>>()
                             .join(", ")
                     );
-                    final_levels.extend(evt_levels);
                 }
+                evt_fallbacks.extend(fallbacks);
+            }
+            let evt_levels = evt.levels;
+            if !evt_levels.is_empty() {
+                println!(
+                    "EVT projected {}: {}",
+                    match side {
+                        TailSide::Upper => "resistances",
+                        TailSide::Lower => "supports",
+                    },
+                    evt_levels
+                        .iter()
+                        .map(|lvl| format!("{:.2}", lvl.price))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                );
+                final_levels.extend(evt_levels);
             }
         }
+    }
 
-        final_levels.sort_by(|a, b| {
-            b.confidence
-                .partial_cmp(&a.confidence)
-                .unwrap_or(std::cmp::Ordering::Equal)
-        });
-        let max_slots = config.max_levels + config.ev_max_levels;
-        if final_levels.len() > max_slots {
-            final_levels.truncate(max_slots);
+    final_levels.sort_by(|a, b| {
+        b.confidence
+            .partial_cmp(&a.confidence)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    let max_slots = config.max_levels + config.ev_max_levels.max(evt_slots);
+    if final_levels.len() > max_slots {
+        final_levels.truncate(max_slots);
+    }
+    // Fallback projections carry no tail probability of their own, so they are
+    // added after the confidence ranking instead of competing in it.
+    final_levels.extend(evt_fallbacks);
+
+    let band = recent_result.mean_atr * config.confidence_band_atr;
+    if !config.gev_blocks.is_empty() {
+        let gev = compute_gev_levels(
+            &bars,
+            &config.gev_blocks,
+            &config.gev_probabilities,
+            &GevSettings {
+                ci_level: config.evt_ci_level,
+                band,
+                min_blocks: config.gev_min_blocks,
+            },
+            current_price,
+        );
+        print_gev_fits(&gev.fits, config.evt_ci_level);
+        final_levels.extend(gev.levels);
+    }
+    if !config.expected_move.is_empty() {
+        match expected_move_levels(
+            &bars,
+            &config.expected_move,
+            &config.expected_move_sigmas,
+            &ExpectedMoveSettings {
+                source: config.expected_move_source,
+                sessions: config.expected_move_sessions,
+                skew: config.expected_move_skew,
+                band,
+            },
+            current_price,
+        ) {
+            Some(moves) => {
+                println!(
+                    "Expected move: {} volatility {:.2}% per session{}",
+                    config.expected_move_source.label(),
+                    moves.volatility * 100.0,
+                    moves
+                        .moments
+                        .map(|(skewness, kurtosis)| format!(
+                            " | skew {skewness:.2}, excess kurtosis {kurtosis:.2}"
+                        ))
+                        .unwrap_or_default()
+                );
+                final_levels.extend(moves.levels);
+            }
+            None => println!("Expected move: not enough sessions to estimate volatility"),
+        }
+    }
+    if config.volume_profile {
+        // Session profiles are scored on the bars after their session only.
+        final_levels.extend(compute_volume_profile_levels(
+            &analysis_bars,
+            &compute_atr(&analysis_bars, config.atr_period),
+            &VolumeProfileSettings {
+                tick_size: config.tick_size,
+                value_area: config.value_area,
+                sessions: config.profile_sessions,
+                smoothing_ticks: config.profile_smoothing_ticks,
+                band,
+                evaluation,
+            },
+            current_price,
+        ));
+    }
+
+    if config.market_profile {
+        let profiles = build_tpo_profiles(
+            &analysis_bars,
+            &MarketProfileSettings {
+                row_size: config.tick_size * config.tpo_row_ticks.max(1) as f64,
+                period_minutes: config.tpo_period_minutes,
+                session_start: rth.start,
+                value_area: config.value_area,
+                sessions: config.tpo_sessions,
+            },
+        );
+        if config.print_tpo {
+            for profile in &profiles {
+                print_tpo_profile(profile);
+            }
+        }
+        // Each profile is scored on the bars after its session only.
+        final_levels.extend(market_profile_levels(
+            &profiles,
+            &analysis_bars,
+            &compute_atr(&analysis_bars, config.atr_period),
+            band,
+            &evaluation,
+            current_price,
+        ));
+    }
+
+    if !config.vwap_anchors.is_empty() || !config.vwap_anchor_times.is_empty() {
+        let mut anchors = builtin_anchors(
+            &bars,
+            &config.vwap_anchors,
+            compute_ath(&bars).map(|ath| ath.timestamp),
+            &recent_result.swings,
+            config.vwap_swings,
+        );
+        for value in &config.vwap_anchor_times {
+            let timestamp = parse_eastern_timestamp(value)
+                .with_context(|| format!("invalid VWAP anchor time {:?}", value))?;
+            match timestamp_anchor(&bars, timestamp) {
+                Some(anchor) => anchors.push(anchor),
+                None => println!("VWAP anchor {} is outside the loaded data; skipped", value),
+            }
+        }
+        // Each VWAP is scored from its own anchor on, so its levels carry
+        // their statistics already.
+        final_levels.extend(anchored_vwap_levels(
+            &bars,
+            &compute_atr(&bars, config.atr_period),
+            &anchors,
+            &VwapSettings {
+                band_count: config.vwap_bands,
+                band,
+                evaluation,
+            },
+            current_price,
+        ));
+    }
+
+    if config.reference_levels {
+        // Reference levels are scored session by session over the full RTH
+        // history and carry pooled statistics, like the pivots below.
+        let mut reference_levels = compute_reference_levels(
+            &bars,
+            &raw_bars,
+            &compute_atr(&bars, config.atr_period),
+            &ReferenceSettings {
+                rth,
+                opening_range_minutes: config.opening_range_minutes,
+                initial_balance_minutes: config.ib_minutes,
+                band,
+                evaluation,
+            },
+            current_price,
+        );
+        if config.merge_reference {
+            let tolerance = recent_result.mean_atr * config.reference_merge_atr;
+            reference_levels =
+                merge_reference_levels(&mut final_levels, reference_levels, tolerance);
+        }
+        final_levels.extend(reference_levels);
+    }
+
+    if config.fib_legs > 0 {
+        let fib_levels = compute_fibonacci_levels(
+            &recent_result.swings,
+            &FibonacciSettings {
+                legs: config.fib_legs,
+                confluence_tolerance: recent_result.mean_atr * config.fib_confluence_atr,
+                min_confluence: config.fib_min_confluence,
+                band,
+            },
+            current_price,
+        );
+        final_levels.extend(evaluate_levels(
+            fib_levels,
+            eval_bars,
+            &eval_atr,
+            &evaluation,
+        ));
+    }
+
+    let mut pivot_summaries = Vec::new();
+    if !config.pivots.is_empty() {
+        // Pivots are scored period by period over the full RTH history, so they
+        // carry their own statistics instead of going through `evaluate_levels`.
+        let pivots = compute_pivot_levels(
+            &bars,
+            &compute_atr(&bars, config.atr_period),
+            &config.pivots,
+            &config.pivot_periods,
+            &PivotSettings { band, evaluation },
+            current_price,
+        );
+        final_levels.extend(pivots.levels);
+        pivot_summaries = pivots.summaries;
+    }
+
+    let mut round_number_tests = Vec::new();
+    if config.round_numbers {
+        let increments = if config.round_increments.is_empty() {
+            auto_increments(current_price)
+        } else {
+            config.round_increments.clone()
+        };
+        let round_numbers = compute_round_number_levels(
+            eval_bars,
+            &eval_atr,
+            &increments,
+            &RoundNumberSettings {
+                max_levels: config.round_max_levels,
+                band,
+                null_offsets: config.round_null_offsets,
+                alpha: config.round_alpha,
+                evaluation,
+            },
+            current_price,
+        );
+        final_levels.extend(round_numbers.levels);
+        round_number_tests = round_numbers.tests;
+    }
+
+    let significance = config.significance.map(|model| SignificanceSettings {
+        model,
+        draws: config.significance_draws,
+        block_length: config.significance_block,
+        fdr: config.significance_fdr,
+        seed: config.significance_seed,
+        atr_period: config.atr_period,
+    });
+    if let Some(settings) = &significance {
+        test_significance(
+            &mut final_levels,
+            eval_bars,
+            &eval_atr,
+            &evaluation,
+            settings,
+        );
+    }
+
+    print_report(&final_levels, current_price, ath, &recent_result.density);
+    print_touch_outcomes(&final_levels);
+    print_trade_stats(&final_levels, &evaluation.trade);
+    if let Some(settings) = &significance {
+        print_significance(&final_levels, settings);
+    }
+    if !pivot_summaries.is_empty() {
+        let mut kde = PerformanceStats::empty();
+        for level in final_levels
+            .iter()
+            .filter(|level| level.source == LevelSource::Density)
+        {
+            kde.merge(&level.performance);
         }
+        print_pivot_comparison(&pivot_summaries, &kde);
+    }
+    if !round_number_tests.is_empty() {
+        print_round_number_tests(&round_number_tests, config.round_alpha);
+    }
 
-        print_report(&final_levels, current_price, ath, &recent_result.density);
+    if config.walk_forward {
+        // Refit on every training window; scoring uses a causal ATR so no
+        // test bar sees a volatility estimate built from later bars.
+        let causal_atr = compute_causal_atr(&bars, config.atr_period);
+        let mut results = Vec::new();
+        for &window in &config.walk_forward_window {
+            for &train_sessions in &config.walk_forward_train {
+                let settings = WalkForwardSettings {
+                    window,
+                    train_sessions,
+                    test_sessions: config.walk_forward_test,
+                    step_sessions: config.walk_forward_step,
+                    evaluation,
+                };
+                results.push(walk_forward(&bars, &causal_atr, &settings, |train| {
+                    run_single_analysis(
+                        train,
+                        config,
+                        AnalysisSettings {
+                            recency_half_life_days: Some(base_half_life),
+                            evaluation,
+                            verbose: false,
+                        },
+                    )
+                    .ok()
+                    .map(|result| result.levels)
+                }));
+            }
+        }
+        print_walk_forward(&results);
     }
 
     Ok(())
@@ -302,8 +669,8 @@ fn filter_by_lookback(bars: &[Bar], lookback_days: usize) -> Vec<Bar> {
         .map(|bar| bar.timestamp - Duration::days(lookback_days as i64))
         .unwrap_or_else(|| New_York.with_ymd_and_hms(1970, 1, 1, 0, 0, 0).unwrap());
     bars.iter()
-        .cloned()
         .filter(|bar| bar.timestamp >= cutoff)
+        .cloned()
         .collect()
 }
 
@@ -366,9 +733,15 @@ fn combine_level_sets(
                     existing.price = (existing.price * existing.confidence
                         + level.price * level.confidence)
                         / total_conf;
+                    existing.zone_low = ((existing.zone_low * existing.confidence
+                        + level.zone_low * level.confidence)
+                        / total_conf)
+                        .min(existing.price);
+                    existing.zone_high = ((existing.zone_high * existing.confidence
+                        + level.zone_high * level.confidence)
+                        / total_conf)
+                        .max(existing.price);
                     existing.confidence = total_conf;
-                    existing.confidence_band =
-                        (existing.confidence_band + level.confidence_band) * 0.5;
                 }
                 merged = true;
                 break;
@@ -469,16 +842,16 @@ fn run_single_analysis(
 
     let swing_count = swings.len();
 
-    if (atr_multiplier_used - config.atr_multiplier).abs() > f64::EPSILON
-        || (min_distance_used - config.min_swing_distance).abs() > f64::EPSILON
-    {
+    let relaxed = (atr_multiplier_used - config.atr_multiplier).abs() > f64::EPSILON
+        || (min_distance_used - config.min_swing_distance).abs() > f64::EPSILON;
+    if settings.verbose && relaxed {
         println!(
             "Detected {} swing points after relaxing atr_multiplier to {:.3} and min_swing_distance to {:.2}",
             swing_count,
             atr_multiplier_used,
             min_distance_used
         );
-    } else {
+    } else if settings.verbose {
         println!("Detected {} swing points", swing_count);
     }
 
@@ -496,12 +869,14 @@ fn run_single_analysis(
     } else {
         swings.clone()
     };
-    println!(
-        "Formed {} price clusters (eps = {:.4}); retained {} swing observations",
-        clusters.len(),
-        epsilon,
-        clustered_swings.len()
-    );
+    if settings.verbose {
+        println!(
+            "Formed {} price clusters (eps = {:.4}); retained {} swing observations",
+            clusters.len(),
+            epsilon,
+            clustered_swings.len()
+        );
+    }
 
     let density_input = if let Some(half_life) = settings.recency_half_life_days {
         let reference = bars
@@ -513,12 +888,44 @@ fn run_single_analysis(
         clustered_swings.clone()
     };
 
-    let density = compute_density_curve(&density_input, config.kde_points);
+    let density = compute_density_curve(
+        &density_input,
+        &DensitySettings {
+            grid_points: config.kde_points,
+            bandwidth_method: config.kde_bandwidth,
+            bandwidth_value: config.kde_bandwidth_value,
+            mean_atr,
+            adaptive: config.kde_adaptive,
+            atr_scaled_kernels: config.kde_atr_kernels,
+            engine: config.kde_engine,
+            kernel: config.kde_kernel,
+            boundary: config.kde_boundary,
+        },
+    );
     if density.is_empty() {
         bail!("density estimation failed; not enough clustered swing data");
     }
+    if settings.verbose
+        && config.kde_engine != KdeEngine::Auto
+        && config.kde_engine != density.engine
+    {
+        println!(
+            "KDE engine {} needs a fixed bandwidth and a grid step of at most bandwidth/8; used {}",
+            config.kde_engine.label(),
+            density.engine.label()
+        );
+    }
 
-    let peaks = detect_peaks(&density);
+    let peaks = detect_peaks(
+        &density,
+        &PeakSettings {
+            min_prominence: config.peak_min_prominence,
+            min_relative_height: config.peak_min_height,
+            min_separation_atr: config.peak_min_separation_atr,
+            mean_atr,
+            zone_fraction: config.zone_fraction,
+        },
+    );
     if peaks.is_empty() {
         bail!("no significant density peaks detected");
     }
@@ -537,19 +944,14 @@ fn run_single_analysis(
         level.distance_from_last = (level.price - current_price).abs();
     }
 
-    let levels = evaluate_levels(
-        levels,
-        bars,
-        &atr,
-        config.reaction_lookahead,
-        config.reaction_move_atr,
-    );
+    let levels = evaluate_levels(levels, bars, &atr, &settings.evaluation);
 
     Ok(AnalysisResult {
         atr,
         mean_atr,
         density,
         levels,
+        swings,
         swing_count,
     })
 }
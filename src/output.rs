@@ -3,7 +3,17 @@ use chrono_tz::Tz;
 use tabled::{settings::Style, Table, Tabled};
 
 use crate::analysis::density::DensityAnalysis;
-use crate::data::{Level, LevelType};
+use crate::analysis::evt::{
+    EvtFitSummary, EvtSample, ExcursionReference, GevBlockFit, TailSide, ThresholdDiagnostic,
+};
+use crate::analysis::gpd::ReturnLevelInterval;
+use crate::analysis::market_profile::TpoProfile;
+use crate::analysis::pivots::PivotSummary;
+use crate::analysis::round_numbers::{format_increment, RoundNumberTest};
+use crate::analysis::significance::SignificanceSettings;
+use crate::analysis::stats::TradeSettings;
+use crate::analysis::walk_forward::WalkForwardResult;
+use crate::data::{Level, LevelType, OutcomeCounts, PerformanceStats, TouchOutcome};
 
 pub struct AthContext {
     pub price: f64,
@@ -14,16 +24,26 @@ pub struct AthContext {
 struct LevelRow {
     #[tabled(rename = "Type")]
     kind: &'static str,
+    #[tabled(rename = "Source")]
+    source: String,
     #[tabled(rename = "Price")]
     price: String,
     #[tabled(rename = "Confidence")]
     confidence: String,
-    #[tabled(rename = "Band")]
-    band: String,
+    #[tabled(rename = "Zone")]
+    zone: String,
+    #[tabled(rename = "Width")]
+    width: String,
     #[tabled(rename = "Hit Rate")]
     hit_rate: String,
     #[tabled(rename = "Touches")]
     touches: String,
+    #[tabled(rename = "Above/Below")]
+    approach: String,
+    #[tabled(rename = "In Zone")]
+    in_zone: String,
+    #[tabled(rename = "1st Touch")]
+    first_touch: String,
     #[tabled(rename = "Avg React")]
     avg_reaction: String,
     #[tabled(rename = "Max Move")]
@@ -51,21 +71,31 @@ pub fn print_report(
     if !density.is_empty() {
         if let (Some(first), Some(last)) = (density.grid.first(), density.grid.last()) {
             println!(
-                "Density Grid: {} points | Peak density {:.4}",
+                "Density Grid: {} points ({}) | Peak density {:.4}",
                 density.grid.len(),
+                density.engine.label(),
                 density.max_density
             );
             let bandwidth_info = if density.bandwidths.is_empty() {
                 "auto".to_string()
             } else {
-                density
-                    .bandwidths
-                    .iter()
-                    .map(|bw| format!("{bw:.4}"))
-                    .collect::<Vec<_>>()
-                    .join(", ")
+                format!(
+                    "{} {}",
+                    density.bandwidth_label,
+                    density
+                        .bandwidths
+                        .iter()
+                        .map(|bw| format!("{bw:.4}"))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
             };
             println!("Bandwidths: {bandwidth_info}");
+            println!(
+                "Kernel: {} | Boundary: {}",
+                density.kernel.label(),
+                density.boundary.label()
+            );
             println!("Price Range: {:.2} to {:.2}", first.price, last.price);
         }
     }
@@ -84,10 +114,29 @@ pub fn print_report(
             } else {
                 "-".to_string()
             };
-            let touches = if tests > 0 {
-                format!("{}", tests)
+            let stats = &level.performance;
+            let (touches, approach, in_zone) = if stats.touches > 0 {
+                (
+                    stats.touches.to_string(),
+                    format!("{}/{}", stats.from_above, stats.from_below),
+                    format!("{:.1}", stats.bars_in_zone as f64 / stats.touches as f64),
+                )
             } else {
-                "-".to_string()
+                ("-".to_string(), "-".to_string(), "-".to_string())
+            };
+            // Pooled levels (pivots) carry one first touch per scored period.
+            let first_touch = match stats.first_tests {
+                0 => "-".to_string(),
+                1 => format!(
+                    "{} {:.2}",
+                    if stats.first_hits > 0 { "hit" } else { "miss" },
+                    stats.first_avg_reaction
+                ),
+                _ => format!(
+                    "{:.1}% {:.2}",
+                    stats.first_hit_rate() * 100.0,
+                    stats.first_avg_reaction
+                ),
             };
             let avg_reaction = if tests > 0 {
                 format!("{:.2}", level.performance.avg_reaction)
@@ -109,11 +158,24 @@ pub fn print_report(
                     LevelType::Support => "Support",
                     LevelType::Resistance => "Resistance",
                 },
+                source: if level.label.is_empty() {
+                    level.source.label().to_string()
+                } else {
+                    format!("{} {}", level.source.label(), level.label)
+                },
                 price: format!("{:.2}", level.price),
                 confidence: format!("{:.2}", level.confidence * 100.0),
-                band: format!("+/-{:.2}", level.confidence_band),
+                zone: format!("{:.2} - {:.2}", level.zone_low, level.zone_high),
+                width: if level.peak_width > 0.0 {
+                    format!("{:.2}", level.peak_width)
+                } else {
+                    "-".to_string()
+                },
                 hit_rate,
                 touches,
+                approach,
+                in_zone,
+                first_touch,
                 avg_reaction,
                 max_move,
                 bars,
@@ -125,3 +187,598 @@ pub fn print_report(
     table.with(Style::rounded());
     println!("\n{table}\n");
 }
+
+#[derive(Tabled)]
+struct OutcomeRow {
+    #[tabled(rename = "Level")]
+    level: String,
+    #[tabled(rename = "Approach")]
+    approach: &'static str,
+    #[tabled(rename = "Tests")]
+    tests: usize,
+    #[tabled(rename = "Bounce")]
+    bounce: String,
+    #[tabled(rename = "Breakout")]
+    breakout: String,
+    #[tabled(rename = "False Break")]
+    false_breakout: String,
+    #[tabled(rename = "Chop")]
+    chop: String,
+    #[tabled(rename = "Approach Hit")]
+    approach_hit: String,
+    #[tabled(rename = "Approach Reaction")]
+    approach_reaction: String,
+}
+
+/// Print touch outcome probabilities per level, split by the side price
+/// approached from.
+pub fn print_touch_outcomes(levels: &[Level]) {
+    let percent =
+        |counts: &OutcomeCounts, outcome| format!("{:.1}%", counts.probability(outcome) * 100.0);
+    let rows: Vec<OutcomeRow> = levels
+        .iter()
+        .flat_map(|level| {
+            let stats = &level.performance;
+            [
+                ("from above", stats.outcomes_above),
+                ("from below", stats.outcomes_below),
+            ]
+            .into_iter()
+            .filter(|(_, counts)| counts.total() > 0)
+            .map(move |(approach, counts)| OutcomeRow {
+                level: format!("{} {:.2}", level.source.label(), level.price),
+                approach,
+                tests: counts.total(),
+                bounce: percent(&counts, TouchOutcome::Bounce),
+                breakout: percent(&counts, TouchOutcome::Breakout),
+                false_breakout: percent(&counts, TouchOutcome::FalseBreakout),
+                chop: percent(&counts, TouchOutcome::Chop),
+                approach_hit: format!("{:.1}%", counts.hit_rate() * 100.0),
+                approach_reaction: format!("{:.2}", counts.avg_reaction()),
+            })
+        })
+        .collect();
+    if rows.is_empty() {
+        return;
+    }
+
+    let mut table = Table::new(rows);
+    table.with(Style::rounded());
+    println!("Touch outcomes by approach side:\n{table}\n");
+}
+
+#[derive(Tabled)]
+struct TradeRow {
+    #[tabled(rename = "Level")]
+    level: String,
+    #[tabled(rename = "Trades")]
+    trades: usize,
+    #[tabled(rename = "Win Rate")]
+    win_rate: String,
+    #[tabled(rename = "Avg R")]
+    avg_r: String,
+    #[tabled(rename = "Expectancy")]
+    expectancy: String,
+    #[tabled(rename = "Profit Factor")]
+    profit_factor: String,
+    #[tabled(rename = "Avg MAE")]
+    avg_mae: String,
+    #[tabled(rename = "Max MAE")]
+    max_mae: String,
+    #[tabled(rename = "Stop Hit")]
+    stop_hit: String,
+    #[tabled(rename = "Bars to Stop")]
+    bars_to_stop: String,
+}
+
+/// Print the per-touch trade simulation and adverse excursions per level.
+pub fn print_trade_stats(levels: &[Level], trade: &TradeSettings) {
+    let rows: Vec<TradeRow> = levels
+        .iter()
+        .filter(|level| level.performance.trades.trades > 0)
+        .map(|level| {
+            let stats = &level.performance;
+            let trades = &stats.trades;
+            TradeRow {
+                level: format!("{} {:.2}", level.source.label(), level.price),
+                trades: trades.trades,
+                win_rate: format!("{:.1}%", trades.win_rate() * 100.0),
+                avg_r: format!("{:+.2}", trades.avg_r()),
+                expectancy: format!("{:+.2}", trades.expectancy()),
+                profit_factor: trades
+                    .profit_factor()
+                    .map_or_else(|| "-".to_string(), |factor| format!("{factor:.2}")),
+                avg_mae: format!("{:.2}", stats.avg_adverse_excursion),
+                max_mae: format!("{:.2}", stats.max_adverse_excursion),
+                stop_hit: format!(
+                    "{:.1}%",
+                    stats.adverse_hits as f64 / stats.tests.max(1) as f64 * 100.0
+                ),
+                bars_to_stop: if stats.adverse_hits > 0 {
+                    format!("{:.1}", stats.avg_bars_to_adverse)
+                } else {
+                    "-".to_string()
+                },
+            }
+        })
+        .collect();
+    if rows.is_empty() {
+        return;
+    }
+
+    let mut table = Table::new(rows);
+    table.with(Style::rounded());
+    println!(
+        "Trade simulation (fade each touch; stop zone + {:.2} ATR, target {:.2} ATR, \
+         time stop {} bars, slippage {:.2}, commission {:.2}):\n{table}\n",
+        trade.stop_atr, trade.target_atr, trade.time_stop, trade.slippage, trade.commission
+    );
+}
+
+#[derive(Tabled)]
+struct WalkForwardRow {
+    #[tabled(rename = "Levels")]
+    name: String,
+    #[tabled(rename = "Folds")]
+    folds: String,
+    #[tabled(rename = "Tests")]
+    tests: usize,
+    #[tabled(rename = "Hit Rate")]
+    hit_rate: String,
+    #[tabled(rename = "1st Hit")]
+    first_hit_rate: String,
+    #[tabled(rename = "Bounce")]
+    bounce: String,
+    #[tabled(rename = "Breakout")]
+    breakout: String,
+    #[tabled(rename = "Win Rate")]
+    win_rate: String,
+    #[tabled(rename = "Avg R")]
+    avg_r: String,
+    #[tabled(rename = "Profit Factor")]
+    profit_factor: String,
+}
+
+/// Print out-of-sample walk-forward statistics for each parameter set, then
+/// per level rank within each set.
+pub fn print_walk_forward(results: &[WalkForwardResult]) {
+    let row = |name: String, folds: String, stats: &PerformanceStats| {
+        let outcomes = stats.outcomes();
+        WalkForwardRow {
+            name,
+            folds,
+            tests: stats.tests,
+            hit_rate: format!("{:.1}%", stats.hit_rate * 100.0),
+            first_hit_rate: format!("{:.1}%", stats.first_hit_rate() * 100.0),
+            bounce: format!("{:.1}%", outcomes.probability(TouchOutcome::Bounce) * 100.0),
+            breakout: format!(
+                "{:.1}%",
+                outcomes.probability(TouchOutcome::Breakout) * 100.0
+            ),
+            win_rate: format!("{:.1}%", stats.trades.win_rate() * 100.0),
+            avg_r: format!("{:+.2}", stats.trades.avg_r()),
+            profit_factor: stats
+                .trades
+                .profit_factor()
+                .map_or_else(|| "-".to_string(), |factor| format!("{factor:.2}")),
+        }
+    };
+    let name = |result: &WalkForwardResult| {
+        let settings = &result.settings;
+        format!(
+            "{} {} / test {} / step {}",
+            settings.window.label(),
+            settings.train_sessions,
+            settings.test_sessions,
+            settings.step_sessions
+        )
+    };
+
+    let rows: Vec<WalkForwardRow> = results
+        .iter()
+        .map(|result| {
+            row(
+                name(result),
+                format!("{} ({} skipped)", result.folds, result.skipped),
+                &result.overall,
+            )
+        })
+        .collect();
+    let mut table = Table::new(rows);
+    table.with(Style::rounded());
+    println!("Walk-forward out-of-sample performance (train sessions / test / step):\n{table}\n");
+
+    for result in results.iter().filter(|result| !result.slots.is_empty()) {
+        let rows: Vec<WalkForwardRow> = result
+            .slots
+            .iter()
+            .map(|slot| {
+                row(
+                    slot.name.clone(),
+                    slot.levels.to_string(),
+                    &slot.performance,
+                )
+            })
+            .collect();
+        let mut table = Table::new(rows);
+        table.with(Style::rounded());
+        println!("Walk-forward by level rank, {}:\n{table}\n", name(result));
+    }
+}
+
+#[derive(Tabled)]
+struct SignificanceRow {
+    #[tabled(rename = "Level")]
+    level: String,
+    #[tabled(rename = "Draws")]
+    draws: usize,
+    #[tabled(rename = "Hit Rate")]
+    hit_rate: String,
+    #[tabled(rename = "Null Hit")]
+    null_hit_rate: String,
+    #[tabled(rename = "p(Hit)")]
+    hit_rate_p: String,
+    #[tabled(rename = "Expectancy")]
+    expectancy: String,
+    #[tabled(rename = "Null Exp")]
+    null_expectancy: String,
+    #[tabled(rename = "p(Exp)")]
+    expectancy_p: String,
+    #[tabled(rename = "FDR Significant")]
+    verdict: &'static str,
+}
+
+/// Print each tested level against its null model with Benjamini-Hochberg
+/// flags.
+pub fn print_significance(levels: &[Level], settings: &SignificanceSettings) {
+    let rows: Vec<SignificanceRow> = levels
+        .iter()
+        .filter_map(|level| {
+            let result = level.significance.as_ref()?;
+            Some(SignificanceRow {
+                level: format!("{} {:.2}", level.source.label(), level.price),
+                draws: result.draws,
+                hit_rate: format!("{:.1}%", result.hit_rate * 100.0),
+                null_hit_rate: format!("{:.1}%", result.null_hit_rate * 100.0),
+                hit_rate_p: format!("{:.3}", result.hit_rate_p),
+                expectancy: format!("{:+.2}", result.expectancy),
+                null_expectancy: format!("{:+.2}", result.null_expectancy),
+                expectancy_p: format!("{:.3}", result.expectancy_p),
+                verdict: match (result.hit_rate_significant, result.expectancy_significant) {
+                    (true, true) => "hit rate + expectancy",
+                    (true, false) => "hit rate",
+                    (false, true) => "expectancy",
+                    (false, false) => "-",
+                },
+            })
+        })
+        .collect();
+    if rows.is_empty() {
+        println!(
+            "Significance vs. {}: no level was tested.\n",
+            settings.model.label()
+        );
+        return;
+    }
+
+    let mut table = Table::new(rows);
+    table.with(Style::rounded());
+    println!(
+        "Significance vs. {} ({} draws, BH FDR {:.2}):\n{table}\n",
+        settings.model.label(),
+        settings.draws,
+        settings.fdr
+    );
+}
+
+/// Print the GPD tail fit with standard errors where available.
+pub fn print_evt_fit(summary: &EvtFitSummary, interval: ReturnLevelInterval, ci_level: f64) {
+    let with_se = |value: f64, se: Option<f64>| match se {
+        Some(se) => format!("{value:.4} ± {se:.4}"),
+        None => format!("{value:.4} (no SE)"),
+    };
+    let lower = summary.side == TailSide::Lower;
+    let sample = match summary.excursion {
+        Some((reference, declustering)) => format!(
+            "{} {} {} {:.2}, {}",
+            summary.sample.label(),
+            if lower { "under" } else { "over" },
+            match reference {
+                ExcursionReference::RollingHigh if lower => "rolling low",
+                _ => reference.label(),
+            },
+            summary.anchor,
+            declustering.label()
+        ),
+        None if lower && summary.sample == EvtSample::Highs => "bar lows".to_string(),
+        None => summary.sample.label().to_string(),
+    };
+    let theta = summary
+        .extremal_index
+        .map(|theta| format!("{theta:.2}"))
+        .unwrap_or_else(|| "n/a".to_string());
+    println!(
+        "EVT {} tail fit ({}, {sample}): threshold {:.2} (q={:.3}), {} peaks beyond it from {} {}, extremal index {theta} | shape {} | scale {} | {:.0}% CI: {}",
+        summary.side.label(),
+        summary.fit.estimator.label(),
+        summary.threshold,
+        summary.threshold_quantile,
+        summary.exceedances,
+        summary.sample_size,
+        summary.unit,
+        with_se(summary.fit.shape, summary.fit.shape_se()),
+        with_se(summary.fit.scale, summary.fit.scale_se()),
+        ci_level * 100.0,
+        interval.label(),
+    );
+}
+
+#[derive(Tabled)]
+struct ThresholdRow {
+    #[tabled(rename = "Quantile")]
+    quantile: String,
+    #[tabled(rename = "Threshold")]
+    threshold: String,
+    #[tabled(rename = "Excesses")]
+    exceedances: usize,
+    #[tabled(rename = "Mean Excess")]
+    mean_excess: String,
+    #[tabled(rename = "Shape")]
+    shape: String,
+    #[tabled(rename = "Mod. Scale")]
+    modified_scale: String,
+    #[tabled(rename = "A²")]
+    anderson_darling: String,
+    #[tabled(rename = "W²")]
+    cramer_von_mises: String,
+    #[tabled(rename = "Stable")]
+    stable: &'static str,
+}
+
+/// Print the candidate thresholds behind automatic EVT threshold selection,
+/// marking the chosen one with `*`.
+pub fn print_evt_thresholds(diagnostics: &[ThresholdDiagnostic], side: TailSide) {
+    let optional = |value: Option<f64>, digits: usize| {
+        value
+            .map(|value| format!("{value:.digits$}"))
+            .unwrap_or_else(|| "-".to_string())
+    };
+    let rows: Vec<ThresholdRow> = diagnostics
+        .iter()
+        .map(|diagnostic| ThresholdRow {
+            quantile: format!(
+                "{:.3}{}",
+                diagnostic.quantile,
+                if diagnostic.selected { " *" } else { "" }
+            ),
+            threshold: format!("{:.2}", diagnostic.threshold),
+            exceedances: diagnostic.exceedances,
+            mean_excess: format!("{:.2}", diagnostic.mean_excess),
+            shape: match (diagnostic.shape, diagnostic.shape_se) {
+                (Some(shape), Some(se)) => format!("{shape:.3} ± {se:.3}"),
+                (shape, _) => optional(shape, 3),
+            },
+            modified_scale: optional(diagnostic.modified_scale, 2),
+            anderson_darling: optional(diagnostic.anderson_darling, 3),
+            cramer_von_mises: optional(diagnostic.cramer_von_mises, 3),
+            stable: if diagnostic.stable { "yes" } else { "no" },
+        })
+        .collect();
+    let mut table = Table::new(rows);
+    table.with(Style::rounded());
+    println!("EVT {} threshold candidates:\n{table}\n", side.label());
+}
+
+#[derive(Tabled)]
+struct GevRow {
+    #[tabled(rename = "Block")]
+    block: String,
+    #[tabled(rename = "Blocks")]
+    blocks: usize,
+    #[tabled(rename = "Open")]
+    anchor: String,
+    #[tabled(rename = "Location")]
+    location: String,
+    #[tabled(rename = "Scale")]
+    scale: String,
+    #[tabled(rename = "Shape")]
+    shape: String,
+    #[tabled(rename = "Return Levels")]
+    return_levels: String,
+}
+
+/// Print GEV block-extreme fits with the prices each block extreme exceeds
+/// at the requested probabilities.
+pub fn print_gev_fits(fits: &[GevBlockFit], ci_level: f64) {
+    if fits.is_empty() {
+        println!("GEV block extremes: not enough completed blocks to fit.\n");
+        return;
+    }
+    let with_se = |value: f64, se: Option<f64>| match se {
+        Some(se) => format!("{value:.3} ± {se:.3}"),
+        None => format!("{value:.3}"),
+    };
+    let rows: Vec<GevRow> = fits
+        .iter()
+        .map(|block| GevRow {
+            block: format!(
+                "{} {}",
+                block.period.label(),
+                match block.side {
+                    TailSide::Upper => "high",
+                    TailSide::Lower => "low",
+                }
+            ),
+            blocks: block.fit.blocks,
+            anchor: format!("{:.2}", block.anchor),
+            location: with_se(block.fit.location, block.fit.location_se()),
+            scale: with_se(block.fit.scale, block.fit.scale_se()),
+            shape: with_se(block.fit.shape, block.fit.shape_se()),
+            return_levels: block
+                .return_levels
+                .iter()
+                .map(|level| match level.bounds {
+                    Some((low, high)) => format!(
+                        "{:.1}%: {:.2} ({low:.2} - {high:.2})",
+                        level.probability * 100.0,
+                        level.price
+                    ),
+                    None => format!("{:.1}%: {:.2}", level.probability * 100.0, level.price),
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+        })
+        .collect();
+    let mut table = Table::new(rows);
+    table.with(Style::rounded());
+    println!(
+        "GEV block extremes (moves from the block open, {:.0}% CI):\n{table}\n",
+        ci_level * 100.0
+    );
+}
+
+#[derive(Tabled)]
+struct ComparisonRow {
+    #[tabled(rename = "Levels")]
+    name: String,
+    #[tabled(rename = "Periods")]
+    periods: String,
+    #[tabled(rename = "Tests")]
+    tests: usize,
+    #[tabled(rename = "Hit Rate")]
+    hit_rate: String,
+    #[tabled(rename = "1st Hit")]
+    first_hit_rate: String,
+    #[tabled(rename = "Avg React")]
+    avg_reaction: String,
+    #[tabled(rename = "Bars")]
+    bars: String,
+}
+
+/// Print pooled historical pivot performance next to the KDE levels' own.
+pub fn print_pivot_comparison(summaries: &[PivotSummary], kde: &PerformanceStats) {
+    let row = |name: String, periods: String, stats: &PerformanceStats| ComparisonRow {
+        name,
+        periods,
+        tests: stats.tests,
+        hit_rate: format!("{:.1}%", stats.hit_rate * 100.0),
+        first_hit_rate: format!("{:.1}%", stats.first_hit_rate() * 100.0),
+        avg_reaction: format!("{:.2}", stats.avg_reaction),
+        bars: format!("{:.1}", stats.avg_reaction_bars),
+    };
+    let mut rows: Vec<ComparisonRow> = summaries
+        .iter()
+        .map(|summary| {
+            row(
+                format!("{} {}", summary.method.label(), summary.period.label()),
+                summary.periods.to_string(),
+                &summary.performance,
+            )
+        })
+        .collect();
+    rows.push(row("KDE".to_string(), "-".to_string(), kde));
+
+    let mut table = Table::new(rows);
+    table.with(Style::rounded());
+    println!("Pivot performance vs. KDE levels:\n{table}\n");
+}
+
+#[derive(Tabled)]
+struct RoundNumberRow {
+    #[tabled(rename = "Increment")]
+    increment: String,
+    #[tabled(rename = "Levels")]
+    levels: usize,
+    #[tabled(rename = "Tests")]
+    tests: usize,
+    #[tabled(rename = "Hit Rate")]
+    hit_rate: String,
+    #[tabled(rename = "Shifted Hit Rate")]
+    null_hit_rate: String,
+    #[tabled(rename = "Shifts")]
+    nulls: usize,
+    #[tabled(rename = "p-value")]
+    p_value: String,
+    #[tabled(rename = "Verdict")]
+    verdict: &'static str,
+}
+
+/// Print whether round numbers react more than the same grid shifted off-round.
+pub fn print_round_number_tests(tests: &[RoundNumberTest], alpha: f64) {
+    let rows: Vec<RoundNumberRow> = tests
+        .iter()
+        .map(|test| RoundNumberRow {
+            increment: format_increment(test.increment),
+            levels: test.levels,
+            tests: test.observed.tests,
+            hit_rate: format!("{:.1}%", test.observed.hit_rate * 100.0),
+            null_hit_rate: if test.nulls > 0 {
+                format!("{:.1}%", test.null_hit_rate * 100.0)
+            } else {
+                "-".to_string()
+            },
+            nulls: test.nulls,
+            p_value: if test.nulls > 0 {
+                format!("{:.3}", test.p_value)
+            } else {
+                "-".to_string()
+            },
+            verdict: if test.nulls == 0 {
+                "no null grid"
+            } else if test.p_value <= alpha {
+                "significant"
+            } else {
+                "not significant"
+            },
+        })
+        .collect();
+    let mut table = Table::new(rows);
+    table.with(Style::rounded());
+    println!("Round-number reactions vs. shifted grids:\n{table}\n");
+}
+
+/// Print a session's letter profile, highest price first, marking the POC
+/// row with `<` and value-area rows with `|`.
+pub fn print_tpo_profile(profile: &TpoProfile) {
+    println!("\n--- TPO Profile {} ---", profile.date.format("%Y-%m-%d"));
+    for (idx, letters) in profile.rows.iter().enumerate().rev() {
+        let price = profile.price_at(idx);
+        let marker = if (price - profile.poc).abs() < 1e-9 {
+            '<'
+        } else if price >= profile.value_area_low - 1e-9 && price <= profile.value_area_high + 1e-9
+        {
+            '|'
+        } else {
+            ' '
+        };
+        println!("{price:>10.2} {marker} {letters}");
+    }
+    println!(
+        "POC {:.2} | Value Area {:.2} - {:.2} | IB {:.2} - {:.2}",
+        profile.poc,
+        profile.value_area_low,
+        profile.value_area_high,
+        profile.ib_low,
+        profile.ib_high
+    );
+    let singles = if profile.single_prints.is_empty() {
+        "none".to_string()
+    } else {
+        profile
+            .single_prints
+            .iter()
+            .map(|(low, high)| format!("{low:.2}-{high:.2}"))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let extreme = |poor: bool, tail: Option<(f64, f64)>| match (poor, tail) {
+        (_, Some((low, high))) => format!("excess {low:.2}-{high:.2}"),
+        (true, None) => "poor".to_string(),
+        (false, None) => "-".to_string(),
+    };
+    println!(
+        "Single prints: {singles} | High: {} | Low: {}",
+        extreme(profile.poor_high, profile.high_tail),
+        extreme(profile.poor_low, profile.low_tail)
+    );
+}